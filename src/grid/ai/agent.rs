@@ -1,6 +1,19 @@
-use crate::model::{Game, PlayerInput};
+use crate::model::{Game, World, Player, PlayerInput, Orientation};
 
 pub trait Agent {
     fn decide(&mut self, game: &Game) -> PlayerInput;
 }
 
+/// Moves worth trying for snake `s`: the three directions that don't reverse it, or nothing if
+/// it's dead.
+pub fn legal_moves(world: &World, s: Player) -> Vec<PlayerInput> {
+    let snake = &world.snakes[s];
+    if !snake.alive {
+        return vec![PlayerInput::DoNothing];
+    }
+    let heading = world.snake_direction(snake.head);
+    Orientation::all().iter()
+        .filter(|d| **d != heading.opposite())
+        .map(|d| PlayerInput::Go(*d))
+        .collect()
+}
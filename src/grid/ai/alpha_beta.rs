@@ -0,0 +1,223 @@
+use std::time::{Duration, Instant};
+
+use crate::model::{Game, World, Cell, PlayerInput, TurnResult, Player};
+use super::{Agent, legal_moves};
+
+type Score = i64;
+
+const WIN: Score = 1_000_000;
+/// Weight of a cell of territory against one segment of length.
+const LENGTH_WEIGHT: Score = 5;
+
+/// Search out of time, the result of the unfinished iteration is dropped.
+struct Timeout;
+
+/// Searches the moves of all snakes with alpha-beta pruning, made for duels.
+///
+/// Moves are simultaneous, the search is paranoid: we move first and the opponents answer with
+/// the move that is worst for us, knowing ours. Iterative deepening goes one turn deeper per
+/// iteration until `max_depth` or the `time_limit` is reached. Positions are evaluated by the
+/// territory of the snakes (cells a snake reaches before the others) and the length difference.
+pub struct AlphaBeta {
+    /// the snake to control
+    pub player: Player,
+    /// number of turns to look ahead
+    pub max_depth: usize,
+    /// stop deepening when the time is up, the deepest finished iteration decides
+    pub time_limit: Option<Duration>
+}
+
+impl AlphaBeta {
+    pub fn new(player: Player, max_depth: usize) -> AlphaBeta {
+        AlphaBeta { player, max_depth, time_limit: None }
+    }
+    /// Every combination of moves of the opponents.
+    fn replies(&self, world: &World) -> Vec<Vec<PlayerInput>> {
+        let mut replies = vec![Vec::new()];
+        for s in (0..world.player_count()).filter(|s| *s != self.player) {
+            let moves = legal_moves(world, s);
+            replies = replies.iter()
+                .flat_map(|reply| moves.iter().map(move |m| { let mut r: Vec<PlayerInput> = reply.clone(); r.push(*m); r }))
+                .collect();
+        }
+        replies
+    }
+    fn inputs(&self, world: &World, action: PlayerInput, reply: &[PlayerInput]) -> Vec<PlayerInput> {
+        let mut inputs = Vec::with_capacity(world.player_count());
+        let mut reply = reply.iter();
+        for s in 0..world.player_count() {
+            inputs.push(if s == self.player { action } else { *reply.next().unwrap() });
+        }
+        inputs
+    }
+    /// Score of a finished game, `None` if it goes on. Earlier wins and later losses are better.
+    fn outcome(&self, game: &Game, depth: usize) -> Option<Score> {
+        let world = &game.world;
+        match world.turn_result {
+            TurnResult::GameOver if world.winners.contains(&self.player) => Some(WIN + depth as Score),
+            TurnResult::GameOver => Some(-WIN - depth as Score),
+            TurnResult::Draw => Some(0),
+            TurnResult::Ok if !world.snakes[self.player].alive => Some(-WIN - depth as Score),
+            TurnResult::Ok => None
+        }
+    }
+    /// Territory and length of our snake compared to the best opponent.
+    fn evaluate(&self, world: &World) -> Score {
+        let topology = world.topology();
+        let passable = |cell: &Cell| matches!(cell, Cell::Empty | Cell::Food(_));
+        let alive: Vec<Player> = (0..world.player_count()).filter(|s| world.snakes[*s].alive).collect();
+        let distances: Vec<_> = alive.iter().map(|s| world.grid.distances(&[world.snakes[*s].head], topology, passable)).collect();
+        let mut territory = vec![0; alive.len()];
+        for (pos, _) in world.grid.iter() {
+            let mut nearest: Option<(usize, usize)> = None;
+            let mut tie = false;
+            for (i, d) in distances.iter().enumerate() {
+                match (d[pos], nearest) {
+                    (Some(d), None) => nearest = Some((i, d)),
+                    (Some(d), Some((_, best))) if d < best => { nearest = Some((i, d)); tie = false },
+                    (Some(d), Some((_, best))) if d == best => tie = true,
+                    _ => ()
+                }
+            }
+            if let (Some((i, _)), false) = (nearest, tie) {
+                territory[i] += 1;
+            }
+        }
+        let own = alive.iter().position(|s| *s == self.player).map_or(0, |i| territory[i]);
+        let best_territory = alive.iter().enumerate().filter(|(_, s)| **s != self.player).map(|(i, _)| territory[i]).max().unwrap_or(0);
+        let own_length = world.snakes[self.player].length as Score;
+        let best_length = alive.iter().filter(|s| **s != self.player).map(|s| world.snakes[*s].length as Score).max().unwrap_or(0);
+        (own - best_territory) as Score + LENGTH_WEIGHT * (own_length - best_length)
+    }
+    /// Best score we can force with `depth` turns left.
+    fn max_value(&self, game: &Game, depth: usize, mut alpha: Score, beta: Score, deadline: Option<Instant>) -> Result<Score, Timeout> {
+        if let Some(score) = self.outcome(game, depth) {
+            return Ok(score);
+        }
+        if depth == 0 {
+            return Ok(self.evaluate(&game.world));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(Timeout);
+        }
+        let mut best = -Score::MAX;
+        for action in legal_moves(&game.world, self.player) {
+            let score = self.min_value(game, action, depth, alpha, beta, deadline)?;
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Ok(best)
+    }
+    /// Score of our `action` when the opponents answer with their best reply.
+    fn min_value(&self, game: &Game, action: PlayerInput, depth: usize, alpha: Score, mut beta: Score, deadline: Option<Instant>) -> Result<Score, Timeout> {
+        let mut worst = Score::MAX;
+        for reply in self.replies(&game.world) {
+            let mut g = game.clone();
+            g.advance(&self.inputs(&game.world, action, &reply));
+            let score = self.max_value(&g, depth-1, alpha, beta, deadline)?;
+            worst = worst.min(score);
+            beta = beta.min(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Ok(worst)
+    }
+    /// Searches `depth` turns deep, `ordered` moves first. Returns the moves sorted by score.
+    fn search(&self, game: &Game, depth: usize, ordered: &[PlayerInput], deadline: Option<Instant>) -> Result<Vec<PlayerInput>, Timeout> {
+        let mut alpha = -Score::MAX;
+        let mut scored = Vec::new();
+        for action in ordered {
+            let score = self.min_value(game, *action, depth, alpha, Score::MAX, deadline)?;
+            alpha = alpha.max(score);
+            scored.push((score, *action));
+        }
+        // stable, equal moves keep the order of the previous iteration
+        scored.sort_by_key(|(score, _)| -score);
+        Ok(scored.into_iter().map(|(_, action)| action).collect())
+    }
+}
+
+impl Agent for AlphaBeta {
+    fn decide(&mut self, game: &Game) -> PlayerInput {
+        if !game.world.snakes[self.player].alive {
+            return PlayerInput::DoNothing;
+        }
+        let deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        let mut ordered = legal_moves(&game.world, self.player);
+        for depth in 1..=self.max_depth.max(1) {
+            match self.search(game, depth, &ordered, deadline) {
+                Ok(moves) => ordered = moves,
+                Err(Timeout) => break
+            }
+        }
+        ordered[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{GameMode, Orientation};
+
+    /// 8x8 duel with walls and `stones`, both snakes start at the given head with the given heading.
+    fn duel(stones: &[(usize, usize)], ours: ((usize, usize), Orientation), theirs: ((usize, usize), Orientation)) -> Game {
+        let mut world = World::with_seed(8, 8, 1);
+        world.wall_collision = true;
+        for stone in stones {
+            world.grid.set(*stone, Cell::Stone);
+        }
+        world.add_snake(ours.0, ours.1).unwrap();
+        world.add_snake(theirs.0, theirs.1).unwrap();
+        world.rehash();
+        let mut game = Game::new(world);
+        game.max_snacks = 0;
+        game
+    }
+
+    #[test]
+    fn avoids_an_immediate_loss() {
+        // left is the wall, right a stone
+        let game = duel(&[(1, 1)], ((1, 0), Orientation::Up), ((6, 6), Orientation::Down));
+        assert_eq!(AlphaBeta::new(0, 3).decide(&game), PlayerInput::Go(Orientation::Up));
+    }
+
+    #[test]
+    fn takes_a_forced_win() {
+        // the opponent crawls along the top edge above a row of stones, our head blocks its way out
+        let mut game = duel(&[(1, 0), (1, 1), (1, 2)], ((1, 3), Orientation::Up), ((0, 1), Orientation::Right));
+        let mut agent = AlphaBeta::new(0, 2);
+        let up = agent.decide(&game);
+        assert_eq!(up, PlayerInput::Go(Orientation::Up));
+        game.advance(&[up, PlayerInput::DoNothing]);
+        game.advance(&[agent.decide(&game), PlayerInput::DoNothing]);
+        assert_eq!(game.world.turn_result, TurnResult::GameOver);
+        assert_eq!(game.world.winners, vec![0]);
+    }
+
+    #[test]
+    fn searches_a_fogged_tron_game() {
+        // going up leads into a pocket, the opponent is far away in the fog
+        let mut world = World::with_seed(10, 10, 1);
+        world.wall_collision = true;
+        world.mode = GameMode::Tron;
+        world.fog_of_war = Some(5);
+        world.add_snake((2, 2), Orientation::Right).unwrap();
+        world.add_snake((8, 8), Orientation::Down).unwrap();
+        for stone in &[(0, 2), (1, 1), (1, 3)] {
+            world.grid.set(*stone, Cell::Stone);
+        }
+        world.rehash();
+        let game = Game::new(world).observed_by(0);
+        let mut after = game.clone();
+        after.advance(&[PlayerInput::Go(Orientation::Up), PlayerInput::DoNothing]);
+        assert_eq!(after.world.turn_result, TurnResult::Ok);
+        after.advance(&[PlayerInput::Go(Orientation::Up), PlayerInput::DoNothing]);
+        assert_eq!(after.world.turn_result, TurnResult::GameOver);
+        assert!(after.world.winners.is_empty());
+        assert_ne!(AlphaBeta::new(0, 3).decide(&game), PlayerInput::Go(Orientation::Up));
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::model::Player;
+use crate::rl::{QAgent, QTable};
+use super::{Agent, SpaceExplorer, OpponentPolicy, AlphaBeta, Mcts, Rollout, RandomAgent, GreedyAgent, HamiltonianAgent};
+
+/// Names of the agents `AgentSpec::build` knows.
+pub const AGENT_KINDS: &[&str] = &["space-explorer", "alpha-beta", "mcts", "greedy", "hamiltonian", "q-table", "random"];
+
+/// Description of an agent on the command line: its kind, optionally followed by parameters,
+/// e.g. `space-explorer:depth=5,opponents=pessimistic`, `alpha-beta:depth=8,time=100` or `random`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentSpec {
+    pub kind: String,
+    pub params: Vec<(String, String)>
+}
+
+impl FromStr for AgentSpec {
+    type Err = String;
+    fn from_str(s: &str) -> Result<AgentSpec, String> {
+        let mut parts = s.splitn(2, ':');
+        let kind = parts.next().unwrap_or("").trim();
+        if !AGENT_KINDS.contains(&kind) {
+            return Err(format!("unknown agent '{}', expected one of: {}", kind, AGENT_KINDS.join(", ")));
+        }
+        let mut params = Vec::new();
+        for param in parts.next().unwrap_or("").split(',').filter(|p| !p.trim().is_empty()) {
+            match param.splitn(2, '=').collect::<Vec<&str>>().as_slice() {
+                [key, value] => params.push((key.trim().to_string(), value.trim().to_string())),
+                _ => return Err(format!("parameter '{}' of agent '{}' should look like key=value", param, kind))
+            }
+        }
+        Ok(AgentSpec { kind: kind.to_string(), params })
+    }
+}
+
+impl fmt::Display for AgentSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        for (i, (key, value)) in self.params.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { ':' } else { ',' }, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl AgentSpec {
+    /// Creates the agent to control snake `player`. Random decisions of the agent only depend
+    /// on `seed`.
+    pub fn build(&self, player: Player, seed: u64) -> Result<Box<dyn Agent>, String> {
+        match self.kind.as_str() {
+            "space-explorer" => {
+                self.check_params(&["depth", "opponents"])?;
+                let mut agent = SpaceExplorer::for_player(self.param("depth", 6)?, player);
+                agent.opponents = self.param("opponents", OpponentPolicy::KeepGoing)?;
+                Ok(Box::new(agent))
+            },
+            "alpha-beta" => {
+                self.check_params(&["depth", "time"])?;
+                let mut agent = AlphaBeta::new(player, self.param("depth", 4)?);
+                // milliseconds per move, 0 searches until the depth is reached
+                let time: u64 = self.param("time", 0)?;
+                agent.time_limit = if time > 0 { Some(Duration::from_millis(time)) } else { None };
+                Ok(Box::new(agent))
+            },
+            "mcts" => {
+                self.check_params(&["iterations", "time", "rollout", "depth", "c"])?;
+                let mut agent = Mcts::new(player, seed);
+                agent.iterations = self.param("iterations", agent.iterations)?;
+                let time: u64 = self.param("time", 0)?;
+                agent.time_limit = if time > 0 { Some(Duration::from_millis(time)) } else { None };
+                agent.rollout_depth = self.param("depth", agent.rollout_depth)?;
+                agent.exploration = self.param("c", agent.exploration)?;
+                // any other kind of agent with its default parameters plays out the games
+                agent.rollout = match self.param("rollout", "random".to_string())?.as_str() {
+                    "random" => Rollout::Random,
+                    "greedy" => Rollout::Greedy,
+                    kind => {
+                        let spec: AgentSpec = kind.parse()?;
+                        spec.build(player, seed)?;
+                        Rollout::Agent(Box::new(move |s| spec.build(s, seed).unwrap()))
+                    }
+                };
+                Ok(Box::new(agent))
+            },
+            "greedy" => {
+                self.check_params(&[])?;
+                Ok(Box::new(GreedyAgent::new(player)))
+            },
+            "hamiltonian" => {
+                self.check_params(&[])?;
+                Ok(Box::new(HamiltonianAgent::new(player)))
+            },
+            "q-table" => {
+                self.check_params(&["file"])?;
+                let file: String = self.param("file", "snakes.qtable".to_string())?;
+                let table = QTable::load(&file).map_err(|e| format!("could not load {}: {}", file, e))?;
+                Ok(Box::new(QAgent::new(player, table)))
+            },
+            "random" => {
+                self.check_params(&[])?;
+                Ok(Box::new(RandomAgent::new(seed)))
+            },
+            kind => Err(format!("unknown agent '{}'", kind))
+        }
+    }
+    /// Value of parameter `key`, `default` if it isn't given.
+    pub fn param<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        match self.params.iter().find(|(k, _)| k == key) {
+            Some((_, value)) => value.parse().map_err(|_| format!("invalid value '{}' for parameter '{}' of {}", value, key, self.kind)),
+            None => Ok(default)
+        }
+    }
+    fn check_params(&self, known: &[&str]) -> Result<(), String> {
+        match self.params.iter().find(|(key, _)| !known.contains(&key.as_str())) {
+            Some((key, _)) => Err(format!("unknown parameter '{}' for {}", key, self.kind)),
+            None => Ok(())
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::model::{Game, PlayerInput, Player, TurnResult};
+use super::{Agent, PathFinder, legal_moves, reachable_space};
+
+/// Heads for the nearest food on the shortest path, a cheap baseline and an easy opponent.
+///
+/// The path is only taken if, after eating, the snake can still reach its tail or at least as
+/// many free cells as it is long, assuming the other snakes keep going. Otherwise, it moves to
+/// where it can reach its tail or the most free cells.
+pub struct GreedyAgent {
+    /// the snake to control
+    pub player: Player
+}
+
+impl GreedyAgent {
+    pub fn new(player: Player) -> GreedyAgent {
+        GreedyAgent { player }
+    }
+    fn inputs(&self, game: &Game, input: PlayerInput) -> Vec<PlayerInput> {
+        (0..game.world.player_count()).map(|s| if s == self.player { input } else { PlayerInput::DoNothing }).collect()
+    }
+    /// Whether the snake died or the game ended without it winning. A lone snake stays alive
+    /// when the game is over.
+    fn has_lost(&self, game: &Game) -> bool {
+        let world = &game.world;
+        !world.snakes[self.player].alive || (world.turn_result != TurnResult::Ok && !world.winners.contains(&self.player))
+    }
+    /// Whether the snake isn't trapped in `game`.
+    fn is_safe(&self, game: &Game) -> bool {
+        let world = &game.world;
+        let snake = &world.snakes[self.player];
+        !self.has_lost(game) && (PathFinder::new(&game.world).to_tail(world, snake.head, self.player).is_some()
+            || reachable_space(world, self.player) >= snake.length)
+    }
+    /// First step of the path to the nearest food, if it is safe.
+    fn towards_food(&self, game: &Game) -> Option<PlayerInput> {
+        let head = game.world.snakes[self.player].head;
+        let path = PathFinder::new(&game.world).nearest_food(&game.world, head)?;
+        let mut after = game.clone();
+        for direction in &path {
+            after.advance(&self.inputs(&after, PlayerInput::Go(*direction)));
+            if self.has_lost(&after) {
+                return None;
+            }
+        }
+        if self.is_safe(&after) { path.first().map(|d| PlayerInput::Go(*d)) } else { None }
+    }
+    /// Move that keeps the tail in reach, or else leaves the most space.
+    fn survive(&self, game: &Game) -> PlayerInput {
+        legal_moves(&game.world, self.player).into_iter()
+            .filter_map(|input| {
+                let mut after = game.clone();
+                after.advance(&self.inputs(game, input));
+                if self.has_lost(&after) {
+                    return None;
+                }
+                let snake = &after.world.snakes[self.player];
+                let tail = PathFinder::new(&after.world).to_tail(&after.world, snake.head, self.player).is_some();
+                Some(((tail, reachable_space(&after.world, self.player)), input))
+            })
+            .max_by_key(|(score, _)| *score)
+            .map_or(PlayerInput::DoNothing, |(_, input)| input)
+    }
+}
+
+impl Agent for GreedyAgent {
+    fn decide(&mut self, game: &Game) -> PlayerInput {
+        if !game.world.snakes[self.player].alive {
+            return PlayerInput::DoNothing;
+        }
+        self.towards_food(game).unwrap_or_else(|| self.survive(game))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{World, Cell, Orientation, Pos};
+
+    /// Snake of length 5 heading left along the top edge from (0, 7) to (0, 3), food in the corner
+    /// at (0, 0) and `stones`.
+    fn food_in_the_corner(stones: &[(usize, usize)]) -> Game {
+        let mut world = World::with_seed(8, 8, 1);
+        world.wall_collision = true;
+        world.add_snake((0, 6), Orientation::Left).unwrap();
+        world.snakes[0].pending_growth = 3;
+        for _ in 0..3 {
+            world.advance(&[Orientation::Left]);
+        }
+        for stone in stones {
+            world.grid.set(*stone, Cell::Stone);
+        }
+        world.rehash();
+        world.place_snack((0, 0), 1).unwrap();
+        let mut game = Game::new(world);
+        game.max_snacks = 0;
+        assert_eq!((game.world.snakes[0].head, game.world.snakes[0].length), (Pos::new(0, 3), 5));
+        game
+    }
+
+    #[test]
+    fn takes_a_safe_path_to_food() {
+        let mut game = food_in_the_corner(&[]);
+        let mut agent = GreedyAgent::new(0);
+        assert_eq!(agent.decide(&game), PlayerInput::Go(Orientation::Left));
+        while game.world.available_snacks() > 0 {
+            game.advance(&[agent.decide(&game)]);
+            assert_eq!(game.world.turn_result, TurnResult::Ok);
+        }
+        assert_eq!(game.world.snakes[0].head, Pos::new(0, 0));
+    }
+
+    #[test]
+    fn refuses_food_in_a_dead_end() {
+        // stones close the corridor to the corner, the snake would be stuck with its tail out of reach
+        let game = food_in_the_corner(&[(1, 0), (1, 1), (1, 2)]);
+        assert_eq!(GreedyAgent::new(0).decide(&game), PlayerInput::Go(Orientation::Down));
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+
+use crate::model::{Game, World, Grid, Cell, Pos, Orientation, PlayerInput, Player, Topology};
+use super::{Agent, reachable_space};
+
+/// Growth of the biggest snack, shortcuts keep this much room to the tail.
+const MAX_SNACK_GROWTH: usize = 3;
+
+/// A closed path through the free cells of a world, visiting each at most once.
+struct Cycle {
+    /// cells in the order of the cycle
+    order: Vec<Pos>,
+    /// position of every cell in `order`, `None` for cells off the cycle
+    index: Grid<Option<usize>>
+}
+
+impl Cycle {
+    /// Builds the longest of the cycles with the 2x2 blocks shifted by a row and/or a column.
+    fn build(world: &World, start: Pos) -> Option<Cycle> {
+        [(0, 0), (0, 1), (1, 0), (1, 1)].iter()
+            .filter_map(|offset| Cycle::with_blocks(world, start, *offset))
+            .max_by_key(|cycle| cycle.len())
+    }
+    /// Builds a cycle through the free cells near `start`.
+    ///
+    /// The grid is split into blocks of 2x2 cells, starting at `offset`. A spanning tree connects
+    /// the blocks without stones, the cycle runs around it. Detours add the cells left over, as
+    /// far as they can be reached and left again. A few cells next to stones and at the edges
+    /// might stay off the cycle.
+    fn with_blocks(world: &World, start: Pos, offset: (usize, usize)) -> Option<Cycle> {
+        let (rows, cols) = (world.grid.rows(), world.grid.cols());
+        let free = |pos: Pos| !matches!(world.grid[pos], Cell::Stone);
+        // top left cell of a block
+        let corner = |b: Pos| Pos::new(offset.0 + 2*b.row, offset.1 + 2*b.col);
+        let (block_rows, block_cols) = (rows.saturating_sub(offset.0) / 2, cols.saturating_sub(offset.1) / 2);
+        let block_free = |b: Pos| (0..4).all(|k| free(Pos::new(corner(b).row + k/2, corner(b).col + k%2)));
+        let blocks = Topology::bounded(block_rows, block_cols);
+        let first = (0..block_rows)
+            .flat_map(|r| (0..block_cols).map(move |c| Pos::new(r, c)))
+            .filter(|b| block_free(*b))
+            .min_by_key(|b| start.manhattan_distance(corner(*b)))?;
+        // every block is a clockwise cycle of its own
+        let mut next: Grid<Option<Pos>> = Grid::from_elem(None, rows, cols);
+        let mut visited = Grid::from_elem(false, block_rows, block_cols);
+        let mut stack = vec![first];
+        visited[first] = true;
+        while let Some(block) = stack.pop() {
+            let Pos { row: top, col: left } = corner(block);
+            next[(top, left)] = Some(Pos::new(top, left+1));
+            next[(top, left+1)] = Some(Pos::new(top+1, left+1));
+            next[(top+1, left+1)] = Some(Pos::new(top+1, left));
+            next[(top+1, left)] = Some(Pos::new(top, left));
+            for (_, b) in blocks.neighbours(block) {
+                if !visited[b] && block_free(b) {
+                    visited[b] = true;
+                    stack.push(b);
+                }
+            }
+        }
+        // merge the cycles of neighbouring blocks along a spanning tree
+        let mut merged = Grid::from_elem(false, block_rows, block_cols);
+        let mut stack = vec![first];
+        merged[first] = true;
+        while let Some(block) = stack.pop() {
+            for (_, other) in blocks.neighbours(block) {
+                if !visited[other] || merged[other] {
+                    continue;
+                }
+                merged[other] = true;
+                stack.push(other);
+                // swap the two facing edges, which run in opposite directions
+                let (a, b) = (corner(block.min(other)), corner(block.max(other)));
+                if block.row == other.row {
+                    // a is left of b
+                    next[(a.row, a.col+1)] = Some(Pos::new(b.row, b.col));
+                    next[(b.row+1, b.col)] = Some(Pos::new(a.row+1, a.col+1));
+                } else {
+                    // a is above b
+                    next[(a.row+1, a.col+1)] = Some(Pos::new(b.row, b.col+1));
+                    next[(b.row, b.col)] = Some(Pos::new(a.row+1, a.col));
+                }
+            }
+        }
+        // detours over free cells next to an edge of the cycle, short ones first
+        while let Some((a, path)) = Cycle::detour(&next, world, 2).or_else(|| Cycle::detour(&next, world, usize::MAX)) {
+            let b = next[a];
+            let mut from = a;
+            for pos in path {
+                next[from] = Some(pos);
+                from = pos;
+            }
+            next[from] = b;
+        }
+        let mut index = Grid::from_elem(None, rows, cols);
+        let mut order = Vec::new();
+        let mut pos = corner(first);
+        while index[pos].is_none() {
+            index[pos] = Some(order.len());
+            order.push(pos);
+            pos = next[pos].expect("cycle is interrupted");
+        }
+        Some(Cycle { order, index })
+    }
+    /// Path of at most `max_len` free cells off the cycle, leading from a cell `a` of the cycle
+    /// to the one after it.
+    fn detour(next: &Grid<Option<Pos>>, world: &World, max_len: usize) -> Option<(Pos, Vec<Pos>)> {
+        let topology = world.topology();
+        let off_cycle = |pos: Pos| next[pos].is_none() && !matches!(world.grid[pos], Cell::Stone);
+        let mut came_from: Grid<Option<Pos>> = Grid::from_elem(None, world.grid.rows(), world.grid.cols());
+        for (a, b) in world.grid.iter().filter_map(|(a, _)| Some((a, next[a]?))) {
+            for (_, c) in topology.neighbours(a).filter(|(_, c)| off_cycle(*c)) {
+                let mut visited = vec![c];
+                let mut queue = VecDeque::new();
+                queue.push_back((c, 1));
+                came_from[c] = Some(c);
+                let mut end = None;
+                while let Some((pos, len)) = queue.pop_front() {
+                    if topology.neighbours(pos).any(|(_, n)| n == b) {
+                        end = Some(pos);
+                        break;
+                    }
+                    if len == max_len {
+                        continue;
+                    }
+                    for (_, n) in topology.neighbours(pos) {
+                        if off_cycle(n) && came_from[n].is_none() {
+                            came_from[n] = Some(pos);
+                            visited.push(n);
+                            queue.push_back((n, len+1));
+                        }
+                    }
+                }
+                let path = end.map(|end| {
+                    let mut path = vec![end];
+                    while path[path.len()-1] != c {
+                        path.push(came_from[path[path.len()-1]].unwrap());
+                    }
+                    path.reverse();
+                    path
+                });
+                for pos in visited {
+                    came_from[pos] = None;
+                }
+                if let Some(path) = path {
+                    return Some((a, path));
+                }
+            }
+        }
+        None
+    }
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+    /// Steps forward along the cycle from `from` to `to`, both on the cycle.
+    fn distance(&self, from: Pos, to: Pos) -> Option<usize> {
+        Some((self.index[to]? + self.len() - self.index[from]?) % self.len())
+    }
+}
+
+/// Follows a Hamiltonian cycle through the board, which fills the board in single player games
+/// and wins them eventually. A benchmark for the other agents.
+///
+/// As long as the body lies along the cycle order, the snake may cut ahead on the cycle towards
+/// food, if that keeps enough cells between head and tail.
+///
+/// Without stones the cycle covers the whole board, or all but one cell if the number of cells
+/// is odd, and single player games are won. Stones leave cells off the cycle, food there
+/// is only fetched by an excursion that fits between head and tail. Once the snake is long, such
+/// food can be out of reach, and food in a dead end can't be eaten by any snake: the game stalls
+/// when no new food spawns.
+pub struct HamiltonianAgent {
+    /// the snake to control
+    pub player: Player,
+    cycle: Option<Cycle>
+}
+
+impl HamiltonianAgent {
+    pub fn new(player: Player) -> HamiltonianAgent {
+        HamiltonianAgent { player, cycle: None }
+    }
+    /// Whether the body cells on the cycle lie in its order, from the tail forward to the head.
+    fn is_aligned(cycle: &Cycle, world: &World, s: Player) -> bool {
+        let cells: Vec<Pos> = world.snake_cells(s).into_iter().filter(|pos| cycle.index[*pos].is_some()).collect();
+        let tail = match cells.first() {
+            Some(tail) => *tail,
+            None => return false
+        };
+        let mut last = 0;
+        for (k, pos) in cells.into_iter().enumerate() {
+            match cycle.distance(tail, pos) {
+                Some(d) if k == 0 || d > last => last = d,
+                _ => return false
+            }
+        }
+        true
+    }
+    /// First step of the shortest way over free cells off the cycle back to a cell of the cycle
+    /// `d` steps ahead of `front` with `fits(d + steps)`. With `food`, the way has to pass food.
+    fn excursion<F: Fn(usize) -> bool>(&self, cycle: &Cycle, world: &World, front: Pos, fits: F, food: bool) -> Option<Orientation> {
+        let topology = world.topology();
+        let head = world.snakes[self.player].head;
+        let is_free = |pos: Pos| matches!(world.grid[pos], Cell::Empty | Cell::Food(_));
+        let mut reached = Grid::from_elem(false, topology.rows, topology.cols);
+        let mut queue = VecDeque::new();
+        if cycle.index[head].is_none() {
+            queue.push_back((head, None, 0, false));
+        } else {
+            for (direction, next) in topology.neighbours(head).filter(|(_, next)| cycle.index[*next].is_none() && is_free(*next)) {
+                let passed = matches!(world.grid[next], Cell::Food(_));
+                reached[next] = true;
+                queue.push_back((next, Some(direction), 1, passed));
+            }
+        }
+        while let Some((pos, first, steps, passed)) = queue.pop_front() {
+            for (direction, next) in topology.neighbours(pos) {
+                if cycle.index[next].is_some() {
+                    let exit = is_free(next) && (passed || !food) && cycle.distance(front, next).is_some_and(|d| fits(d + steps));
+                    if exit {
+                        return Some(first.unwrap_or(direction));
+                    }
+                } else if next != head && !reached[next] && is_free(next) {
+                    let first = first.unwrap_or(direction);
+                    let passed = passed || matches!(world.grid[next], Cell::Food(_));
+                    reached[next] = true;
+                    queue.push_back((next, Some(first), steps+1, passed));
+                }
+            }
+        }
+        None
+    }
+    /// Neighbour of the head to move to: the farthest one ahead on the cycle that doesn't pass the
+    /// nearest food and leaves room to the tail, or else the next cell of the cycle.
+    ///
+    /// Food off the cycle is fetched with an excursion over the cells off the cycle, back to the
+    /// cycle further ahead.
+    fn along_cycle(&self, cycle: &Cycle, world: &World, free: &[(Orientation, Pos)]) -> Option<Orientation> {
+        let snake = &world.snakes[self.player];
+        let on_cycle: Vec<Pos> = world.snake_cells(self.player).into_iter().filter(|pos| cycle.index[*pos].is_some()).collect();
+        // distances count from the foremost body cell on the cycle, the head unless on a detour
+        let (tail, front) = (*on_cycle.first()?, *on_cycle.last()?);
+        let to_tail = match cycle.distance(front, tail)? {
+            0 => cycle.len(),
+            d => d
+        };
+        let to_food = world.grid.iter()
+            .filter(|(_, cell)| matches!(cell, Cell::Food(_)))
+            .filter_map(|(food, _)| cycle.distance(front, food))
+            .min()
+            .unwrap_or(1);
+        let room = snake.pending_growth + MAX_SNACK_GROWTH + 1;
+        let fits = |d: usize| d > 0 && d + room < to_tail;
+        if front != snake.head {
+            return self.excursion(cycle, world, front, fits, true).or_else(|| self.excursion(cycle, world, front, fits, false));
+        }
+        if let Some(direction) = self.excursion(cycle, world, front, fits, true) {
+            return Some(direction);
+        }
+        // the cells skipped by a shortcut stay empty until the tail passes them, while the snake
+        // keeps growing: shortcuts are only taken while head and tail are less than half of
+        // the cycle apart
+        let shortcut = |d: usize| 2 * (cycle.len() - to_tail + d + room) < cycle.len();
+        free.iter()
+            .filter_map(|(direction, next)| Some((cycle.distance(front, *next)?, *direction)))
+            .filter(|(d, _)| *d == 1 || (*d <= to_food && shortcut(*d)))
+            .max_by_key(|(d, _)| *d)
+            .map(|(_, direction)| direction)
+    }
+}
+
+impl Agent for HamiltonianAgent {
+    fn decide(&mut self, game: &Game) -> PlayerInput {
+        let world = &game.world;
+        let snake = &world.snakes[self.player];
+        if !snake.alive {
+            return PlayerInput::DoNothing;
+        }
+        // stones can turn up during the game, then the cycle is rebuilt
+        let broken = self.cycle.as_ref().is_none_or(|cycle| cycle.order.iter().any(|pos| matches!(world.grid[*pos], Cell::Stone)));
+        if broken {
+            self.cycle = Cycle::build(world, snake.head);
+        }
+        let free: Vec<(Orientation, Pos)> = world.topology().neighbours(snake.head)
+            .filter(|(_, next)| matches!(world.grid[*next], Cell::Empty | Cell::Food(_)))
+            .collect();
+        let on_cycle = match &self.cycle {
+            Some(cycle) if HamiltonianAgent::is_aligned(cycle, world, self.player) => self.along_cycle(cycle, world, &free),
+            _ => None
+        };
+        if let Some(direction) = on_cycle {
+            return PlayerInput::Go(direction);
+        }
+        // off the cycle order, e.g. at the start: follow the cycle if possible, otherwise keep
+        // as much space as possible until the body lines up
+        let successor = |pos: Pos| self.cycle.as_ref().and_then(|cycle| cycle.distance(snake.head, pos)) == Some(1);
+        let direction = free.iter()
+            .max_by_key(|(direction, _)| {
+                let mut after = world.clone();
+                let mut directions: Vec<Orientation> = after.snakes.iter().map(|s| if s.alive { after.snake_direction(s.head) } else { Orientation::Down }).collect();
+                directions[self.player] = *direction;
+                after.advance(&directions);
+                (reachable_space(&after, self.player), successor(after.snakes[self.player].head))
+            })
+            .map(|(direction, _)| *direction);
+        direction.map_or(PlayerInput::DoNothing, PlayerInput::Go)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TurnResult;
+
+    #[test]
+    fn wins_single_player_games() {
+        // size, walls, stones
+        for &(size, walls, stones) in &[(8, true, 0), (9, false, 0), (10, true, 0), (9, false, 2), (10, false, 2)] {
+            for seed in 0..3 {
+                let mut world = World::with_seed(size, size, seed);
+                world.wall_collision = walls;
+                world.add_snake((1, 2), Orientation::Down).unwrap();
+                world.place_stones_randomly(stones);
+                let mut game = Game::new(world);
+                let mut agent = HamiltonianAgent::new(0);
+                while game.world.turn_result == TurnResult::Ok && game.iteration() < 100_000 {
+                    game.advance(&[agent.decide(&game)]);
+                }
+                assert_eq!(game.world.turn_result, TurnResult::GameOver, "{}x{} with {} stones, seed {}", size, size, stones, seed);
+                assert_eq!(game.world.winners, vec![0]);
+            }
+        }
+    }
+}
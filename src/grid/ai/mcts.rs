@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+use crate::model::{Game, World, Cell, Player, PlayerInput, TurnResult};
+use super::{Agent, legal_moves};
+
+/// How the snakes move in the playouts after leaving the tree.
+pub enum Rollout {
+    /// uniformly random moves that don't reverse
+    Random,
+    /// towards the nearest food, avoiding cells that kill right away
+    Greedy,
+    /// agents created for every snake, by player
+    Agent(Box<dyn Fn(Player) -> Box<dyn Agent>>)
+}
+
+/// Visits and summed rewards of one move of one snake.
+#[derive(Clone, Copy, Default)]
+struct Stats {
+    visits: usize,
+    reward: f64
+}
+
+struct Node {
+    visits: usize,
+    /// legal moves of every snake
+    moves: Vec<Vec<PlayerInput>>,
+    /// statistics of the moves of every snake
+    stats: Vec<Vec<Stats>>,
+    /// by the index of the move of every snake
+    children: HashMap<Vec<usize>, Node>
+}
+
+impl Node {
+    fn new(world: &World) -> Node {
+        let moves: Vec<Vec<PlayerInput>> = (0..world.player_count()).map(|s| legal_moves(world, s)).collect();
+        let stats = moves.iter().map(|m| vec![Stats::default(); m.len()]).collect();
+        Node { visits: 0, moves, stats, children: HashMap::new() }
+    }
+}
+
+/// Monte Carlo tree search with UCT.
+///
+/// Moves are simultaneous, so the search is decoupled: at every node each snake picks its own
+/// move by UCT on its own statistics, the joint move leads to the child. A playout scores 1 for
+/// a win, 0 for a loss or death and 0.5 for a draw. Playouts cut off after `rollout_depth` turns
+/// score surviving snakes between 0.5 and 1, more the more they grew.
+pub struct Mcts {
+    /// the snake to control
+    pub player: Player,
+    /// playouts per move
+    pub iterations: usize,
+    /// stop earlier when the time is up
+    pub time_limit: Option<Duration>,
+    pub rollout: Rollout,
+    /// turns per playout
+    pub rollout_depth: usize,
+    /// UCT exploration constant
+    pub exploration: f64,
+    rng: SmallRng
+}
+
+impl Mcts {
+    pub fn new(player: Player, seed: u64) -> Mcts {
+        Mcts {
+            player,
+            iterations: 1000,
+            time_limit: None,
+            rollout: Rollout::Random,
+            rollout_depth: 20,
+            exploration: 2f64.sqrt(),
+            rng: SmallRng::seed_from_u64(seed)
+        }
+    }
+    /// Descends the tree from `node`, expands one node and plays out from there. Returns the
+    /// reward of every snake.
+    fn iterate(&mut self, node: &mut Node, mut game: Game, start_lengths: &[usize], agents: &mut [Box<dyn Agent>]) -> Vec<f64> {
+        if game.world.turn_result != TurnResult::Ok {
+            node.visits += 1;
+            return rewards(&game.world, start_lengths);
+        }
+        if node.visits == 0 {
+            node.visits += 1;
+            return self.playout(game, start_lengths, agents);
+        }
+        let joint: Vec<usize> = (0..node.moves.len()).map(|s| self.select(node, s)).collect();
+        let inputs: Vec<PlayerInput> = joint.iter().enumerate().map(|(s, m)| node.moves[s][*m]).collect();
+        game.advance(&inputs);
+        let child = node.children.entry(joint.clone()).or_insert_with(|| Node::new(&game.world));
+        let rewards = self.iterate(child, game, start_lengths, agents);
+        node.visits += 1;
+        for (s, m) in joint.iter().enumerate() {
+            let stats = &mut node.stats[s][*m];
+            stats.visits += 1;
+            stats.reward += rewards[s];
+        }
+        rewards
+    }
+    /// Move of snake `s` with the best upper confidence bound, untried moves first.
+    fn select(&mut self, node: &Node, s: Player) -> usize {
+        let stats = &node.stats[s];
+        let untried: Vec<usize> = (0..stats.len()).filter(|m| stats[*m].visits == 0).collect();
+        if !untried.is_empty() {
+            return untried[self.rng.gen_range(0, untried.len())];
+        }
+        let log_visits = (node.visits as f64).ln();
+        let ucb = |stats: &Stats| stats.reward / stats.visits as f64 + self.exploration * (log_visits / stats.visits as f64).sqrt();
+        (0..stats.len()).max_by(|a, b| ucb(&stats[*a]).partial_cmp(&ucb(&stats[*b])).unwrap()).unwrap()
+    }
+    fn playout(&mut self, mut game: Game, start_lengths: &[usize], agents: &mut [Box<dyn Agent>]) -> Vec<f64> {
+        for _ in 0..self.rollout_depth {
+            if game.world.turn_result != TurnResult::Ok {
+                break;
+            }
+            let inputs: Vec<PlayerInput> = (0..game.world.player_count()).map(|s| match self.rollout {
+                _ if !game.world.snakes[s].alive => PlayerInput::DoNothing,
+                Rollout::Random => {
+                    let moves = legal_moves(&game.world, s);
+                    moves[self.rng.gen_range(0, moves.len())]
+                },
+                Rollout::Greedy => self.greedy(&game.world, s),
+                Rollout::Agent(_) => agents[s].decide(&game)
+            }).collect();
+            game.advance(&inputs);
+        }
+        rewards(&game.world, start_lengths)
+    }
+    /// Safe move of snake `s` closest to food, a random one if none is safe.
+    fn greedy(&mut self, world: &World, s: Player) -> PlayerInput {
+        let moves = legal_moves(world, s);
+        let safe = moves.iter().cloned().filter(|input| match input {
+            PlayerInput::Go(direction) => match world.step(world.snakes[s].head, *direction) {
+                Some(next) => matches!(world.grid[next], Cell::Empty | Cell::Food(_)),
+                None => false
+            },
+            _ => false
+        });
+        match safe.min_by_key(|input| food_distance(world, s, *input)) {
+            Some(input) => input,
+            None => moves[self.rng.gen_range(0, moves.len())]
+        }
+    }
+}
+
+/// Distance from the cell `player` moves to with `input` to the nearest food.
+fn food_distance(world: &World, player: usize, input: PlayerInput) -> usize {
+    let topology = world.topology();
+    let next = match input {
+        PlayerInput::Go(direction) => world.snakes[player].head.step(direction, topology),
+        _ => Some(world.snakes[player].head)
+    };
+    let next = match next {
+        Some(next) => next,
+        None => return usize::MAX
+    };
+    world.grid.iter()
+        .filter(|(_, cell)| matches!(cell, Cell::Food(_)))
+        .map(|(food, _)| topology.distance(next, food))
+        .min()
+        .unwrap_or(0)
+}
+
+/// Reward of every snake: 1 for a win, 0 for a loss or death, 0.5 for a draw and between 0.5 and
+/// 1 for a snake alive in a running game, depending on its growth.
+fn rewards(world: &World, start_lengths: &[usize]) -> Vec<f64> {
+    (0..world.player_count()).map(|s| match world.turn_result {
+        TurnResult::GameOver if world.winners.contains(&s) => 1.0,
+        TurnResult::Draw => 0.5,
+        _ if !world.snakes[s].alive => 0.0,
+        TurnResult::GameOver => 0.0,
+        TurnResult::Ok => {
+            let growth = world.snakes[s].length.saturating_sub(start_lengths[s]) as f64;
+            0.5 + 0.5 * growth / (growth + 1.0)
+        }
+    }).collect()
+}
+
+impl Agent for Mcts {
+    fn decide(&mut self, game: &Game) -> PlayerInput {
+        if !game.world.snakes[self.player].alive {
+            return PlayerInput::DoNothing;
+        }
+        let deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        let start_lengths: Vec<usize> = game.world.snakes.iter().map(|snake| snake.length).collect();
+        let mut agents: Vec<Box<dyn Agent>> = match &self.rollout {
+            Rollout::Agent(create) => (0..game.world.player_count()).map(create).collect(),
+            _ => Vec::new()
+        };
+        let mut root = Node::new(&game.world);
+        for _ in 0..self.iterations.max(1) {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+            self.iterate(&mut root, game.clone(), &start_lengths, &mut agents);
+        }
+        let stats = &root.stats[self.player];
+        let best = (0..stats.len()).max_by_key(|m| stats[*m].visits).unwrap();
+        root.moves[self.player][best]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{GameEvent, Orientation};
+
+    /// Whether snake 0 runs into something with `input` while the other snake keeps going.
+    fn collides(game: &Game, input: PlayerInput) -> bool {
+        let mut after = game.clone();
+        after.advance(&[input, PlayerInput::DoNothing]);
+        after.events().iter().any(|e| matches!(e, GameEvent::Collision(0, _)))
+    }
+
+    #[test]
+    fn never_collides_when_a_safe_move_exists() {
+        // heads next to walls and stones, the opponent is far away
+        let positions = [
+            ((1, 0), Orientation::Up, (1, 1)),
+            ((0, 3), Orientation::Right, (1, 3)),
+            ((6, 7), Orientation::Down, (7, 6)),
+            ((4, 4), Orientation::Left, (4, 3))
+        ];
+        for (head, heading, stone) in &positions {
+            let mut world = World::with_seed(8, 8, 1);
+            world.wall_collision = true;
+            world.grid.set(*stone, Cell::Stone);
+            world.add_snake(*head, *heading).unwrap();
+            world.add_snake((5, 0), Orientation::Up).unwrap();
+            world.rehash();
+            let mut game = Game::new(world);
+            game.max_snacks = 0;
+            assert!(legal_moves(&game.world, 0).iter().any(|input| !collides(&game, *input)));
+            for seed in 0..4 {
+                let mut agent = Mcts::new(0, seed);
+                agent.iterations = 200;
+                let input = agent.decide(&game);
+                assert!(!collides(&game, input), "{:?} from {:?} with seed {}", input, head, seed);
+            }
+        }
+    }
+}
@@ -1,5 +1,21 @@
 mod agent;
 mod space_explorer;
+mod alpha_beta;
+mod mcts;
+mod random_agent;
+mod greedy_agent;
+mod hamiltonian_agent;
+mod pathfinding;
+mod observation;
+mod factory;
 
 pub use agent::*;
 pub use space_explorer::*;
+pub use alpha_beta::*;
+pub use mcts::*;
+pub use random_agent::*;
+pub use greedy_agent::*;
+pub use hamiltonian_agent::*;
+pub use pathfinding::*;
+pub use observation::*;
+pub use factory::*;
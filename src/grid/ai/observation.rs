@@ -0,0 +1,210 @@
+use crate::model::{Game, World, Grid, Cell, Pos, Vector, Orientation, Topology, PlayerInput, Player};
+use super::Agent;
+
+/// What a snake sees in a cell of its observation window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObservedCell {
+    Empty,
+    /// outside of a grid with walls
+    Wall,
+    Food(usize),
+    Stone,
+    OwnHead,
+    OwnBody,
+    OwnTail,
+    EnemyHead,
+    EnemyBody,
+    EnemyTail
+}
+
+/// Builds square windows around the head of a snake.
+#[derive(Debug, Clone, Copy)]
+pub struct ObservationBuilder {
+    /// side length of the window, odd so the head is in the middle
+    pub size: usize,
+    /// turn the window so the snake always faces up
+    pub rotate: bool
+}
+
+/// A window of the world around the head of a snake, the head is in the center.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub cells: Grid<ObservedCell>,
+    /// world orientation that points up in the window
+    pub heading: Orientation,
+    head: Pos,
+    topology: Topology
+}
+
+impl ObservationBuilder {
+    pub fn new(size: usize) -> ObservationBuilder {
+        debug_assert!(size % 2 == 1);
+        ObservationBuilder { size, rotate: false }
+    }
+    pub fn observe(&self, world: &World, player: Player) -> Observation {
+        let head = world.snakes[player].head;
+        let heading = match (self.rotate, world.grid[head]) {
+            (true, Cell::Snake(_, direction)) => direction,
+            _ => Orientation::Up
+        };
+        let mut observation = Observation {
+            cells: Grid::from_elem(ObservedCell::Wall, self.size, self.size),
+            heading,
+            head,
+            topology: world.topology()
+        };
+        for row in 0..self.size {
+            for col in 0..self.size {
+                if let Some(pos) = observation.to_world(row, col) {
+                    observation.cells.set((row, col), ObservedCell::from_world(world, player, pos));
+                }
+            }
+        }
+        observation
+    }
+}
+
+impl ObservedCell {
+    /// The cell at `pos`, as seen by `player`.
+    pub fn from_world(world: &World, player: Player, pos: Pos) -> ObservedCell {
+        match world.grid[pos] {
+            Cell::Empty => ObservedCell::Empty,
+            Cell::Food(growth_value) => ObservedCell::Food(growth_value),
+            Cell::Stone => ObservedCell::Stone,
+            Cell::Snake(s, _) if s == player && world.is_head(s, pos) => ObservedCell::OwnHead,
+            Cell::Snake(s, _) if s == player && world.is_tail(s, pos) => ObservedCell::OwnTail,
+            Cell::Snake(s, _) if s == player => ObservedCell::OwnBody,
+            Cell::Snake(s, _) if world.is_head(s, pos) => ObservedCell::EnemyHead,
+            Cell::Snake(s, _) if world.is_tail(s, pos) => ObservedCell::EnemyTail,
+            Cell::Snake(..) => ObservedCell::EnemyBody
+        }
+    }
+}
+
+impl Observation {
+    pub fn size(&self) -> usize {
+        self.cells.rows()
+    }
+    pub fn get(&self, row: usize, col: usize) -> ObservedCell {
+        self.cells[(row, col)]
+    }
+    /// World position of a cell in the window, `None` if it lies behind a wall.
+    pub fn to_world(&self, row: usize, col: usize) -> Option<Pos> {
+        let center = (self.size() / 2) as isize;
+        let mut offset = Vector::new(row as isize - center, col as isize - center);
+        let mut up = Orientation::Up;
+        while up != self.heading {
+            offset = offset.clockwise();
+            up = up.clockwise();
+        }
+        self.head.offset(offset, self.topology)
+    }
+    /// World orientation of a direction in the window.
+    pub fn to_world_direction(&self, direction: Orientation) -> Orientation {
+        let mut up = Orientation::Up;
+        let mut direction = direction;
+        while up != self.heading {
+            direction = direction.clockwise();
+            up = up.clockwise();
+        }
+        direction
+    }
+    /// Translates an input given in window directions to one for the game.
+    pub fn to_world_input(&self, input: PlayerInput) -> PlayerInput {
+        match input {
+            PlayerInput::Go(direction) => PlayerInput::Go(self.to_world_direction(direction)),
+            input => input
+        }
+    }
+}
+
+/// An agent that decides based on its observation window only.
+pub trait ObservingAgent {
+    /// Inputs with a direction are relative to the window.
+    fn decide(&mut self, observation: &Observation) -> PlayerInput;
+}
+
+/// Lets an `ObservingAgent` play snake `player`, it only sees the window around the head. Under
+/// the fog of war rule (`World::fog_of_war`), a window of the same size hides nothing it can see.
+pub struct WindowedAgent<A: ObservingAgent> {
+    pub agent: A,
+    pub player: Player,
+    pub builder: ObservationBuilder
+}
+
+impl<A: ObservingAgent> WindowedAgent<A> {
+    pub fn new(agent: A, player: Player, builder: ObservationBuilder) -> WindowedAgent<A> {
+        WindowedAgent { agent, player, builder }
+    }
+}
+
+impl<A: ObservingAgent> Agent for WindowedAgent<A> {
+    fn decide(&mut self, game: &Game) -> PlayerInput {
+        let observation = self.builder.observe(&game.world, self.player);
+        let input = self.agent.decide(&observation);
+        observation.to_world_input(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 7x7 world with walls, a snake in the middle heading right, food straight ahead of it and a
+    /// stone on its left.
+    fn heading_right() -> World {
+        let mut world = World::with_seed(7, 7, 1);
+        world.wall_collision = true;
+        world.add_snake((3, 3), Orientation::Right).unwrap();
+        world.grid.set((3, 4), Cell::Food(2));
+        world.grid.set((2, 3), Cell::Stone);
+        world
+    }
+
+    #[test]
+    fn window_is_aligned_with_the_world() {
+        let observation = ObservationBuilder::new(3).observe(&heading_right(), 0);
+        assert_eq!(observation.heading, Orientation::Up);
+        assert_eq!(observation.get(1, 1), ObservedCell::OwnHead);
+        assert_eq!(observation.get(1, 2), ObservedCell::Food(2));
+        assert_eq!(observation.get(0, 1), ObservedCell::Stone);
+        assert_eq!(observation.get(1, 0), ObservedCell::OwnTail);
+        assert_eq!(observation.to_world(0, 0), Some(Pos::new(2, 2)));
+        assert_eq!(observation.to_world_input(PlayerInput::Go(Orientation::Left)), PlayerInput::Go(Orientation::Left));
+    }
+
+    #[test]
+    fn rotated_window_faces_up() {
+        let builder = ObservationBuilder { size: 3, rotate: true };
+        let observation = builder.observe(&heading_right(), 0);
+        assert_eq!(observation.heading, Orientation::Right);
+        assert_eq!(observation.get(1, 1), ObservedCell::OwnHead);
+        // straight ahead is up, the left of the snake is left and its tail is below
+        assert_eq!(observation.get(0, 1), ObservedCell::Food(2));
+        assert_eq!(observation.get(1, 0), ObservedCell::Stone);
+        assert_eq!(observation.get(2, 1), ObservedCell::OwnTail);
+        assert_eq!(observation.to_world(0, 1), Some(Pos::new(3, 4)));
+        assert_eq!(observation.to_world(0, 0), Some(Pos::new(2, 4)));
+        assert_eq!(observation.to_world_direction(Orientation::Up), Orientation::Right);
+        assert_eq!(observation.to_world_direction(Orientation::Left), Orientation::Up);
+        assert_eq!(observation.to_world_input(PlayerInput::Go(Orientation::Right)), PlayerInput::Go(Orientation::Down));
+        assert_eq!(observation.to_world_input(PlayerInput::TurnLeft), PlayerInput::TurnLeft);
+    }
+
+    #[test]
+    fn cells_behind_walls_are_walls() {
+        let mut world = World::with_seed(7, 7, 1);
+        world.wall_collision = true;
+        world.add_snake((1, 0), Orientation::Down).unwrap();
+        let observation = ObservationBuilder::new(5).observe(&world, 0);
+        assert_eq!(observation.get(0, 2), ObservedCell::Wall);
+        assert_eq!(observation.get(2, 1), ObservedCell::Wall);
+        assert_eq!(observation.get(1, 2), ObservedCell::OwnTail);
+        assert_eq!(observation.get(2, 3), ObservedCell::Empty);
+        // on a torus the window wraps around instead
+        world.wall_collision = false;
+        let observation = ObservationBuilder::new(5).observe(&world, 0);
+        assert_eq!(observation.to_world(0, 2), Some(Pos::new(6, 0)));
+        assert_eq!(observation.get(2, 1), ObservedCell::Empty);
+    }
+}
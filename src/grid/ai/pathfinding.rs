@@ -0,0 +1,193 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::model::{GameMode, World, Grid, Cell, Pos, Orientation, Topology, Player};
+
+/// Never becomes free.
+const BLOCKED: usize = usize::MAX;
+
+/// Shortest paths through a world, for a snake moving one cell per turn.
+///
+/// Snake segments are obstacles only until the tail has retracted past them: a cell is free for
+/// a snake arriving in turn `t` (the first move is turn 1) if the segment on it is gone after
+/// turn `t-1`. Growth from food eaten on the way isn't taken into account.
+///
+/// The searches never revisit a cell: every cell is entered at most once, at the earliest turn it
+/// is reached. Paths that have to wait for a segment to retract, e.g. by circling, aren't found.
+pub struct PathFinder {
+    topology: Topology,
+    /// turn from which on a cell can be entered, `BLOCKED` if never
+    free_from: Grid<usize>
+}
+
+impl PathFinder {
+    pub fn new(world: &World) -> PathFinder {
+        let mut free_from = world.grid.map(|cell| match cell {
+            Cell::Empty | Cell::Food(_) => 0,
+            Cell::Stone | Cell::Snake(..) => BLOCKED
+        });
+        if world.mode != GameMode::Tron {
+            for (s, snake) in world.snakes.iter().enumerate() {
+                if !snake.alive {
+                    continue;
+                }
+                // segment k (counted from the tail) is gone after turn k+1+pending_growth
+                for (k, pos) in world.snake_cells(s).into_iter().enumerate() {
+                    free_from[pos] = k + snake.pending_growth + 2;
+                }
+            }
+        }
+        PathFinder { topology: world.topology(), free_from }
+    }
+    /// First turn in which `pos` can be entered, `None` if it stays blocked.
+    pub fn free_from(&self, pos: Pos) -> Option<usize> {
+        match self.free_from[pos] {
+            BLOCKED => None,
+            turn => Some(turn)
+        }
+    }
+    /// Whether `pos` can be entered in turn `turn`.
+    pub fn is_free(&self, pos: Pos, turn: usize) -> bool {
+        self.free_from[pos] <= turn
+    }
+    /// Shortest path from `start` to the nearest cell for which `is_target` holds. `start` itself
+    /// is never a target.
+    pub fn bfs<F: Fn(Pos) -> bool>(&self, start: Pos, is_target: F) -> Option<Vec<Orientation>> {
+        let mut came_from = Grid::from_elem(None, self.topology.rows, self.topology.cols);
+        let mut visited = Grid::from_elem(false, self.topology.rows, self.topology.cols);
+        let mut queue = VecDeque::new();
+        visited[start] = true;
+        queue.push_back((start, 0));
+        while let Some((pos, turn)) = queue.pop_front() {
+            for (direction, next) in self.topology.neighbours(pos) {
+                // blocked cells stay unvisited, they might be free for a later arrival
+                if visited[next] || !self.is_free(next, turn+1) {
+                    continue;
+                }
+                visited[next] = true;
+                came_from[next] = Some(direction);
+                if is_target(next) {
+                    return Some(self.path(&came_from, start, next));
+                }
+                queue.push_back((next, turn+1));
+            }
+        }
+        None
+    }
+    /// Shortest path from `start` to `goal`, guided by the distance on an empty grid.
+    pub fn a_star(&self, start: Pos, goal: Pos) -> Option<Vec<Orientation>> {
+        if start == goal {
+            return Some(Vec::new());
+        }
+        let mut came_from = Grid::from_elem(None, self.topology.rows, self.topology.cols);
+        let mut best = Grid::from_elem(usize::MAX, self.topology.rows, self.topology.cols);
+        let mut open = BinaryHeap::new();
+        best[start] = 0;
+        open.push(Reverse((self.topology.distance(start, goal), 0, start)));
+        while let Some(Reverse((_, turn, pos))) = open.pop() {
+            if pos == goal {
+                return Some(self.path(&came_from, start, goal));
+            }
+            if turn > best[pos] {
+                continue;
+            }
+            for (direction, next) in self.topology.neighbours(pos) {
+                if turn+1 >= best[next] || !self.is_free(next, turn+1) {
+                    continue;
+                }
+                best[next] = turn+1;
+                came_from[next] = Some(direction);
+                open.push(Reverse((turn + 1 + self.topology.distance(next, goal), turn+1, next)));
+            }
+        }
+        None
+    }
+    /// Shortest path from `start` to the nearest food.
+    pub fn nearest_food(&self, world: &World, start: Pos) -> Option<Vec<Orientation>> {
+        self.bfs(start, |pos| matches!(world.grid[pos], Cell::Food(_)))
+    }
+    /// Shortest path from `start` to the current tail of snake `s`, if it has moved away by the
+    /// time of arrival.
+    pub fn to_tail(&self, world: &World, start: Pos, s: Player) -> Option<Vec<Orientation>> {
+        self.a_star(start, world.snakes[s].tail)
+    }
+    /// Follows `came_from` back from `goal` to `start`.
+    fn path(&self, came_from: &Grid<Option<Orientation>>, start: Pos, goal: Pos) -> Vec<Orientation> {
+        let mut path = Vec::new();
+        let mut pos = goal;
+        while pos != start {
+            let direction = came_from[pos].expect("path is interrupted");
+            path.push(direction);
+            pos = self.topology.step(pos, direction.opposite()).expect("path leaves the grid");
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::GameMode;
+    use Orientation::*;
+
+    /// Bounded grid with a snake in column 1: tail at (0, 1), head at (1, 1).
+    fn world(rows: usize) -> World {
+        let mut world = World::with_seed(rows, 4, 0);
+        world.wall_collision = true;
+        world.add_snake((1, 1), Down).unwrap();
+        world
+    }
+
+    /// Where `path` leads from `start`.
+    fn follow(world: &World, start: Pos, path: &[Orientation]) -> Pos {
+        path.iter().fold(start, |pos, direction| pos.step(*direction, world.topology()).unwrap())
+    }
+
+    #[test]
+    fn tail_frees_up_just_in_time() {
+        let world = world(2);
+        let paths = PathFinder::new(&world);
+        assert_eq!(paths.free_from(Pos::new(0, 1)), Some(2));
+        assert_eq!(paths.free_from(Pos::new(1, 1)), Some(3));
+        assert!(!paths.is_free(Pos::new(0, 1), 1));
+        // the tail cell is entered in turn 2, right after the tail left it
+        assert_eq!(paths.bfs(Pos::new(1, 0), |pos| pos == Pos::new(0, 2)), Some(vec![Up, Right, Right]));
+        assert_eq!(paths.a_star(Pos::new(1, 0), Pos::new(0, 2)), Some(vec![Up, Right, Right]));
+        // waiting a turn would work, but cells aren't revisited
+        assert_eq!(paths.bfs(Pos::new(0, 0), |pos| pos == Pos::new(0, 2)), None);
+    }
+
+    #[test]
+    fn pending_growth_delays_the_tail() {
+        let mut world = world(3);
+        assert_eq!(PathFinder::new(&world).a_star(Pos::new(1, 0), Pos::new(0, 2)).map(|path| path.len()), Some(3));
+        world.snakes[0].pending_growth = 1;
+        let paths = PathFinder::new(&world);
+        assert_eq!(paths.free_from(Pos::new(0, 1)), Some(3));
+        // around the snake instead of over its tail
+        assert_eq!(paths.a_star(Pos::new(1, 0), Pos::new(0, 2)), Some(vec![Down, Right, Right, Up, Up]));
+        assert_eq!(paths.bfs(Pos::new(1, 0), |pos| pos == Pos::new(0, 2)).map(|path| path.len()), Some(5));
+    }
+
+    #[test]
+    fn path_to_own_tail() {
+        let mut world = world(3);
+        let path = PathFinder::new(&world).to_tail(&world, world.snakes[0].head, 0).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(follow(&world, world.snakes[0].head, &path), world.snakes[0].tail);
+        // in tron mode the tail never moves away
+        world.mode = GameMode::Tron;
+        assert_eq!(PathFinder::new(&world).to_tail(&world, world.snakes[0].head, 0), None);
+    }
+
+    #[test]
+    fn paths_wrap_around_a_torus() {
+        let world = World::with_seed(3, 4, 0);
+        let paths = PathFinder::new(&world);
+        assert_eq!(paths.bfs(Pos::new(0, 0), |pos| pos == Pos::new(0, 3)), Some(vec![Left]));
+        let path = paths.a_star(Pos::new(0, 0), Pos::new(2, 3)).unwrap();
+        assert_eq!(path.len(), 2);
+        assert_eq!(follow(&world, Pos::new(0, 0), &path), Pos::new(2, 3));
+    }
+}
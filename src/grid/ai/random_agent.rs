@@ -0,0 +1,23 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+use crate::model::{Game, PlayerInput, Orientation};
+use super::Agent;
+
+/// Picks a direction uniformly at random every turn. A baseline for the other agents.
+pub struct RandomAgent {
+    rng: SmallRng
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> RandomAgent {
+        RandomAgent { rng: SmallRng::seed_from_u64(seed) }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn decide(&mut self, _game: &Game) -> PlayerInput {
+        let directions = Orientation::all();
+        PlayerInput::Go(directions[self.rng.gen_range(0, directions.len())])
+    }
+}
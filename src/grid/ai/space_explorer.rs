@@ -1,66 +1,155 @@
-use crate::model::{Game, World, PlayerInput, Orientation, TurnResult};
+use std::str::FromStr;
+
+use crate::model::{Game, GameMode, World, Cell, PlayerInput, Orientation, TurnResult, Player};
 use super::Agent;
-use rand::Rng;
 
 /// negative values are bad, 0 is neutral, positive values are good. Scaling not fixed
 type Score = i32;
 
+/// How `SpaceExplorer` expects the other snakes to move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpponentPolicy {
+    /// opponents keep their heading
+    KeepGoing,
+    /// the next move of the nearest opponent is the worst one for us, afterwards it keeps going
+    /// like the others
+    Pessimistic
+}
+
+impl FromStr for OpponentPolicy {
+    type Err = ();
+    fn from_str(s: &str) -> Result<OpponentPolicy, ()> {
+        match s {
+            "keep-going" => Ok(OpponentPolicy::KeepGoing),
+            "pessimistic" => Ok(OpponentPolicy::Pessimistic),
+            _ => Err(())
+        }
+    }
+}
+
 /// finds the best decision by exploring the decision tree up to k levels
 pub struct SpaceExplorer {
     tree_depth: usize,
+    /// the snake to control
+    pub player: Player,
+    pub opponents: OpponentPolicy,
     possible_actions: Vec<PlayerInput>,
     snake_length: usize
 }
 
 impl SpaceExplorer {
     pub fn new(tree_depth: usize) -> SpaceExplorer {
+        SpaceExplorer::for_player(tree_depth, 0)
+    }
+    pub fn for_player(tree_depth: usize, player: Player) -> SpaceExplorer {
         use PlayerInput::*;
         use Orientation::*;
         SpaceExplorer {
-            tree_depth: tree_depth,
+            tree_depth,
+            player,
+            opponents: OpponentPolicy::KeepGoing,
             possible_actions: vec![Go(Left), Go(Right), Go(Up), Go(Down)],
             snake_length: 0
         }
     }
-    fn score_node(&self, world: &World) -> Score {
+    fn score_node(&self, game: &Game) -> Score {
+        let world = &game.world;
+        let snake = &world.snakes[self.player];
         match world.turn_result {
-            TurnResult::Ok => 4*(world.snakes[0].length - self.snake_length + world.snakes[0].pending_growth) as Score + 1,
-            TurnResult::GameOver => if world.winners.len() == 1 { 1000000 } else { -1000000 }
+            // eliminated, while the others play on
+            TurnResult::Ok if !snake.alive => -1000000,
+            // snakes grow with every step in tron mode, what counts is the space left to move
+            TurnResult::Ok if world.mode == GameMode::Tron => reachable_space(world, self.player) as Score + 1,
+            // signed, being cut can leave us shorter than at the start of the search
+            TurnResult::Ok => 4*(snake.length as Score - self.snake_length as Score + snake.pending_growth as Score) + 1,
+            TurnResult::GameOver => if world.winners.contains(&self.player) { 1000000 } else { -1000000 }
             _ => -100000
         }
     }
+    /// Inputs of all snakes for our `action`, the opponents play `replies`.
+    fn inputs(&self, game: &Game, action: PlayerInput, replies: &[PlayerInput]) -> Vec<PlayerInput> {
+        let mut inputs = vec![PlayerInput::DoNothing; game.world.player_count()];
+        let opponents = (0..inputs.len()).filter(|s| *s != self.player);
+        for (s, reply) in opponents.zip(replies) {
+            inputs[s] = *reply;
+        }
+        inputs[self.player] = action;
+        inputs
+    }
+    /// Moves of the nearest opponent that is still alive, the other opponents keep going. Only
+    /// one opponent is considered, all combinations would grow exponentially with their number.
+    fn replies(&self, game: &Game) -> Vec<Vec<PlayerInput>> {
+        let world = &game.world;
+        let head = world.snakes[self.player].head;
+        let opponents: Vec<Player> = (0..world.player_count()).filter(|s| *s != self.player).collect();
+        let nearest = opponents.iter().enumerate()
+            .filter(|(_, s)| world.snakes[**s].alive)
+            .min_by_key(|(_, s)| world.topology().distance(head, world.snakes[**s].head))
+            .map(|(i, _)| i);
+        match nearest {
+            Some(nearest) => self.possible_actions.iter().map(|m| {
+                let mut reply = vec![PlayerInput::DoNothing; opponents.len()];
+                reply[nearest] = *m;
+                reply
+            }).collect(),
+            None => vec![Vec::new()]
+        }
+    }
     fn score_children(&self, game: &Game, depth: usize) -> Vec<Score> {
         let mut scores : Vec<Score> = vec![0; self.possible_actions.len()];
         for (i, a) in self.possible_actions.iter().enumerate() {
             let mut g = game.clone();
-            let commands = vec![*a];
-            g.advance(&commands);
+            g.advance(&self.inputs(game, *a, &[]));
             scores[i] = self.score_subtree(&g, depth);
         }
         scores
     }
+    /// Like `score_children`, but every action is scored with the worst reply of the opponents.
+    fn score_children_pessimistic(&self, game: &Game, depth: usize) -> Vec<Score> {
+        let replies = self.replies(game);
+        self.possible_actions.iter().map(|a| {
+            replies.iter().map(|reply| {
+                let mut g = game.clone();
+                g.advance(&self.inputs(game, *a, reply));
+                self.score_subtree(&g, depth)
+            }).min().unwrap()
+        }).collect()
+    }
     fn score_subtree(&self, game: &Game, depth: usize) -> Score {
         if depth == 0 {
-            return self.score_node(&game.world);
+            return self.score_node(game);
         }
-        let node_score = self.score_node(&game.world);
+        let node_score = self.score_node(game);
         if node_score < 0 {
             return node_score;
         }
-        let scores = self.score_children(&game, depth-1);
+        let scores = self.score_children(game, depth-1);
         //let best = scores.iter().cloned().fold(-1./0. as Score /* -inf */, Score::max);
         let best = scores.iter().cloned().max_by(|a, b| a.partial_cmp(b).unwrap()).unwrap();
-        return Score::max(best, (2*depth) as Score * node_score);
+        // every continuation ends badly, e.g. in a dead end or bitten by an opponent
+        if best < 0 {
+            return best;
+        }
+        Score::max(best, (2*depth) as Score * node_score)
     }
 }
 
+/// Number of free cells the head of `player` can reach.
+pub(crate) fn reachable_space(world: &World, player: usize) -> usize {
+    let region = world.grid.flood_fill(world.snakes[player].head, world.topology(), |cell| matches!(cell, Cell::Empty | Cell::Food(_)));
+    // the head itself isn't free
+    region.iter().filter(|(_, reachable)| **reachable).count() - 1
+}
 
 impl Agent for SpaceExplorer {
     fn decide(&mut self, game: &Game) -> PlayerInput {
         let mut g = game.clone();
         g.max_snacks = 0;
-        self.snake_length = g.world.snakes[0].length;
-        let scores = self.score_children(&g, self.tree_depth);
+        self.snake_length = g.world.snakes[self.player].length;
+        let scores = match self.opponents {
+            OpponentPolicy::Pessimistic if g.world.player_count() > 1 => self.score_children_pessimistic(&g, self.tree_depth),
+            _ => self.score_children(&g, self.tree_depth)
+        };
         let mut top_scores = Vec::new();
         let mut max_score = -1000000;
         for (i, s) in scores.iter().enumerate() {
@@ -76,7 +165,63 @@ impl Agent for SpaceExplorer {
         //let mut rng = rand::thread_rng();
         //let picked_action = rng.gen_range(0, top_scores.len());
         let picked_action = 0;
-        return self.possible_actions[top_scores[picked_action]];
+        self.possible_actions[top_scores[picked_action]]
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Remains, GameEvent, Pos};
+
+    #[test]
+    fn survives_being_cut() {
+        // bites shorten snakes below the length they had when the search started
+        for seed in 0..3 {
+            let mut world = World::with_seed(10, 10, seed);
+            world.snake_cutting = Some(Remains::Food(1));
+            let mut agents: Vec<SpaceExplorer> = (0..3).map(|s| SpaceExplorer::for_player(2, s)).collect();
+            for (s, agent) in agents.iter_mut().enumerate() {
+                world.add_snake((1, s*3+2), Orientation::Down).unwrap();
+                agent.opponents = if s == 0 { OpponentPolicy::Pessimistic } else { OpponentPolicy::KeepGoing };
+            }
+            let mut game = Game::new(world);
+            game.max_snacks = 10;
+            while game.world.turn_result == TurnResult::Ok && game.iteration() < 100 {
+                let inputs: Vec<PlayerInput> = agents.iter_mut().map(|agent| agent.decide(&game)).collect();
+                game.advance(&inputs);
+                assert_eq!(game.world.validate(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn avoids_a_bite() {
+        // our snake runs down column 4, the opponent comes from the left edge
+        let mut world = World::with_seed(10, 10, 1);
+        world.wall_collision = true;
+        world.snake_cutting = Some(Remains::Food(1));
+        world.add_snake((2, 4), Orientation::Down).unwrap();
+        world.snakes[0].pending_growth = 3;
+        for _ in 0..3 {
+            world.advance(&[Orientation::Down]);
+        }
+        world.add_snake((5, 1), Orientation::Right).unwrap();
+        world.rehash();
+        let mut game = Game::new(world);
+        game.max_snacks = 0;
+        assert_eq!((game.world.snakes[0].head, game.world.snakes[0].length), (Pos::new(5, 4), 5));
+
+        // turning left lets the opponent bite into the body behind our head a turn later
+        let mut bitten = game.clone();
+        bitten.advance(&[PlayerInput::Go(Orientation::Left), PlayerInput::DoNothing]);
+        bitten.advance(&[PlayerInput::Go(Orientation::Up), PlayerInput::DoNothing]);
+        assert!(bitten.events().iter().any(|e| matches!(e, GameEvent::SnakeCut(1, 0, 4))));
+        assert_eq!(bitten.world.snakes[0].length, 1);
+
+        let mut agent = SpaceExplorer::for_player(1, 0);
+        agent.opponents = OpponentPolicy::Pessimistic;
+        assert_ne!(agent.decide(&game), PlayerInput::Go(Orientation::Left));
+    }
+}
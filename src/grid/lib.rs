@@ -1,7 +1,12 @@
+#![allow(clippy::result_unit_err)]
+
 extern crate termion;
 extern crate rand;
 
 pub mod model;
 pub mod view;
 pub mod ai;
+pub mod replay;
+pub mod rl;
+pub mod rating;
 
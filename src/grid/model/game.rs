@@ -1,9 +1,25 @@
-use super::{World, TurnResult, Orientation, GameEvent};
+use super::{World, Player, GameMode, TurnResult, Orientation, GameEvent};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlayerInput {
     DoNothing,
-    Go(Orientation)
+    Go(Orientation),
+    /// turn left relative to the current heading of the snake
+    TurnLeft,
+    /// turn right relative to the current heading of the snake
+    TurnRight
+}
+
+impl PlayerInput {
+    /// The direction a snake heading in `heading` moves to with this input.
+    pub fn orientation(self, heading: Orientation) -> Orientation {
+        match self {
+            PlayerInput::DoNothing => heading,
+            PlayerInput::Go(orientation) => orientation,
+            PlayerInput::TurnLeft => heading.counter_clockwise(),
+            PlayerInput::TurnRight => heading.clockwise()
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -11,6 +27,7 @@ pub struct Game {
     pub world: World,
     iteration: usize,
     orientations: Vec<Orientation>,
+    events: Vec<GameEvent>,
     pub lose_on_collision: bool,
     pub max_snacks: usize
 }
@@ -18,17 +35,39 @@ pub struct Game {
 impl Game {
     pub fn new(world: World) -> Game {
         Game {
-            world: world,
+            world,
             iteration: 0,
             orientations: Vec::new(),
+            events: Vec::new(),
             lose_on_collision: true,
             max_snacks: 2
         }
     }
+    /// Continues a game at the given iteration, e.g. when loading a replay.
+    pub(crate) fn resume(world: World, iteration: usize) -> Game {
+        let mut game = Game::new(world);
+        game.iteration = iteration;
+        game
+    }
     pub fn iteration(&self) -> usize {
         self.iteration
     }
-    pub fn advance(&mut self, directions: &Vec<PlayerInput>) {
+    /// The game as snake `player` sees it, see `World::observed_by`. This is what agents decide on.
+    pub fn observed_by(&self, player: Player) -> Game {
+        Game {
+            world: self.world.observed_by(player),
+            iteration: self.iteration,
+            orientations: self.orientations.clone(),
+            events: self.events.clone(),
+            lose_on_collision: self.lose_on_collision,
+            max_snacks: self.max_snacks
+        }
+    }
+    /// Events of the last turn.
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+    pub fn advance(&mut self, directions: &[PlayerInput]) {
         // make sure all invariants with the world hold
         if self.orientations.len() < self.world.player_count() {
             self.orientations = vec![Orientation::Down; self.world.player_count()];
@@ -37,41 +76,50 @@ impl Game {
         self.world.losers.clear();
         // read world state for default behavior
         for i in 0..self.world.player_count() {
-            self.orientations[i] = self.world.snake_direction(self.world.snakes[i].head);
+            if self.world.snakes[i].alive {
+                self.orientations[i] = self.world.snake_direction(self.world.snakes[i].head);
+            }
         }
         // apply user choice
-        for i in 0..self.world.player_count() {
-            self.orientations[i] = match directions[i] {
-                PlayerInput::DoNothing => self.orientations[i],
-                PlayerInput::Go(orient) => orient
-            }
+        for (orientation, input) in self.orientations.iter_mut().zip(directions) {
+            *orientation = input.orientation(*orientation);
         }
         // Physics
         let events = self.world.advance(&self.orientations);
         // Game events
         // TODO: could be more exciting ...
-        if self.iteration % 16 == 0 && self.world.available_snacks() < self.max_snacks {
-            self.world.place_snack_randomly((2*self.iteration)%3+1).unwrap();
+        if self.world.mode == GameMode::Classic && self.iteration % 16 == 0 && self.world.available_snacks() < self.max_snacks {
+            // the world might be too crowded, the snack is placed in a later turn then
+            self.world.place_snack_randomly((2*self.iteration)%3+1).ok();
         }
         // Apply game rules
         let mut players_collided = vec![false; self.world.player_count()];
-        for e in events {
-            match e {
-                GameEvent::Collision(s, _) => players_collided[s] = true,
-                _ => ()
-            };
-        }
-        let all_collided = players_collided.iter().fold(true, |sum, x| sum && *x);
-        let some_collided = players_collided.iter().fold(false, |sum, x| sum || *x);
-        if self.lose_on_collision && all_collided && self.world.snakes.len() > 1 {
+        for e in &events {
+            if let GameEvent::Collision(s, _) = e {
+                players_collided[*s] = true;
+            }
+        }
+        self.events = events;
+        let all_collided = players_collided.iter().all(|x| *x);
+        let some_collided = players_collided.iter().any(|x| *x);
+        let elimination = (self.world.dead_snake_food.is_some() || self.world.mode == GameMode::Tron) && self.world.snakes.len() > 1;
+        if self.lose_on_collision && elimination {
+            if self.eliminate(&players_collided) {
+                return
+            }
+        }
+        else if self.lose_on_collision && all_collided && self.world.snakes.len() > 1 {
             self.world.turn_result = TurnResult::Draw;
             return
         }
         else if self.lose_on_collision && some_collided {
             let mut winners = Vec::new();
             let mut losers = Vec::new();
-            for i in 0..self.world.player_count() {
-                if !players_collided[i] {
+            for (i, collided) in players_collided.iter().enumerate() {
+                if self.world.snakes[i].ghost {
+                    continue;
+                }
+                if !collided {
                     winners.push(i);
                 } else {
                     losers.push(i);
@@ -91,5 +139,163 @@ impl Game {
         self.iteration += 1;
         self.world.turn_result = TurnResult::Ok
     }
+    /// Takes the collided snakes out of the game, their bodies stay as obstacles unless
+    /// `World::dead_snake_food` is set. Returns true if the game is over, because at most one snake is
+    /// left.
+    fn eliminate(&mut self, players_collided: &[bool]) -> bool {
+        for (s, collided) in players_collided.iter().enumerate() {
+            if *collided {
+                let food_cells = self.world.kill_snake(s);
+                self.events.push(GameEvent::SnakeDied(s));
+                if food_cells > 0 {
+                    self.events.push(GameEvent::BodyTurnedToFood(s, food_cells));
+                }
+            }
+        }
+        match self.world.alive_count() {
+            0 => {
+                for (i, collided) in players_collided.iter().enumerate() {
+                    if !collided {
+                        self.world.losers.push(i);
+                    }
+                }
+                self.world.turn_result = TurnResult::Draw;
+            },
+            1 => {
+                for (i, snake) in self.world.snakes.iter().enumerate() {
+                    if snake.ghost {
+                        continue;
+                    }
+                    if snake.alive {
+                        self.world.winners.push(i);
+                    } else {
+                        self.world.losers.push(i);
+                    }
+                }
+                self.world.turn_result = TurnResult::GameOver;
+            },
+            _ => return false
+        }
+        true
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Cell, Pos};
+
+    /// 8x8 world with walls and dead snake food, all snakes heading down along the left or right
+    /// edge. No snacks are placed.
+    fn three_snakes() -> Game {
+        let mut world = World::with_seed(8, 8, 1);
+        world.wall_collision = true;
+        world.dead_snake_food = Some(2);
+        for head in &[(1, 0), (1, 7), (4, 0)] {
+            world.add_snake(*head, Orientation::Down).unwrap();
+        }
+        let mut game = Game::new(world);
+        game.max_snacks = 0;
+        game
+    }
+
+    const LEFT: PlayerInput = PlayerInput::Go(Orientation::Left);
+    const RIGHT: PlayerInput = PlayerInput::Go(Orientation::Right);
+    const AHEAD: PlayerInput = PlayerInput::DoNothing;
+
+    #[test]
+    fn dead_snakes_turn_into_food() {
+        let mut game = three_snakes();
+        game.advance(&[LEFT, AHEAD, AHEAD]);
+        assert_eq!(game.world.turn_result, TurnResult::Ok);
+        assert!(game.events().iter().any(|e| matches!(e, GameEvent::SnakeDied(0))));
+        assert!(game.events().iter().any(|e| matches!(e, GameEvent::BodyTurnedToFood(0, 2))));
+        assert!(matches!(game.world.grid[(0, 0)], Cell::Food(2)) && matches!(game.world.grid[(1, 0)], Cell::Food(2)));
+        assert_eq!(game.world.alive_count(), 2);
+        assert_eq!(game.world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn last_snake_alive_wins() {
+        let mut game = three_snakes();
+        game.advance(&[LEFT, AHEAD, AHEAD]);
+        game.advance(&[AHEAD, RIGHT, AHEAD]);
+        assert_eq!(game.world.turn_result, TurnResult::GameOver);
+        assert_eq!(game.world.winners, vec![2]);
+        assert_eq!(game.world.losers, vec![0, 1]);
+    }
+
+    #[test]
+    fn last_snakes_dying_together_draw() {
+        let mut game = three_snakes();
+        game.advance(&[LEFT, AHEAD, AHEAD]);
+        game.advance(&[AHEAD, RIGHT, LEFT]);
+        assert_eq!(game.world.turn_result, TurnResult::Draw);
+        assert!(game.world.winners.is_empty());
+        assert_eq!(game.world.losers, vec![0]);
+    }
+
+    fn tron(rows: usize, cols: usize, heads: &[(usize, usize)]) -> Game {
+        let mut world = World::with_seed(rows, cols, 1);
+        world.wall_collision = true;
+        world.mode = GameMode::Tron;
+        for head in heads {
+            world.add_snake(*head, Orientation::Down).unwrap();
+        }
+        Game::new(world)
+    }
+
+    #[test]
+    fn tron_trail_stays() {
+        let mut game = tron(8, 8, &[(1, 1)]);
+        for _ in 0..3 {
+            game.advance(&[AHEAD]);
+        }
+        assert_eq!(game.world.snakes[0].length, 5);
+        assert_eq!(game.world.snakes[0].tail, Pos::new(0, 1));
+        for row in 0..5 {
+            assert!(matches!(game.world.grid[(row, 1)], Cell::Snake(0, _)));
+        }
+        assert_eq!(game.world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn tron_spawns_no_snacks() {
+        let mut game = tron(40, 8, &[(1, 1)]);
+        for _ in 0..34 {
+            game.advance(&[AHEAD]);
+            assert_eq!(game.world.turn_result, TurnResult::Ok);
+        }
+        assert_eq!(game.world.available_snacks(), 0);
+        assert!(game.world.grid.iter().all(|(_, cell)| !matches!(cell, Cell::Food(_))));
+    }
+
+    #[test]
+    fn tron_last_snake_alive_wins() {
+        // snake 1 runs into the trail of snake 0
+        let mut game = tron(8, 8, &[(1, 1), (1, 2)]);
+        game.advance(&[AHEAD, AHEAD]);
+        assert_eq!(game.world.turn_result, TurnResult::Ok);
+        game.advance(&[AHEAD, LEFT]);
+        assert_eq!(game.world.turn_result, TurnResult::GameOver);
+        assert_eq!(game.world.winners, vec![0]);
+        assert_eq!(game.world.losers, vec![1]);
+    }
+
+    #[test]
+    fn tron_single_snake_wins_when_the_grid_is_filled() {
+        // 4x4 grid, the game is won at a length of 3*3
+        let mut game = tron(4, 4, &[(1, 0)]);
+        let up = PlayerInput::Go(Orientation::Up);
+        let moves = [AHEAD, AHEAD, RIGHT, up, AHEAD, AHEAD];
+        for input in &moves {
+            game.advance(&[*input]);
+            assert_eq!(game.world.turn_result, TurnResult::Ok);
+        }
+        assert_eq!(game.world.snakes[0].length, 8);
+        game.advance(&[RIGHT]);
+        assert_eq!(game.world.snakes[0].length, 9);
+        assert_eq!(game.world.turn_result, TurnResult::GameOver);
+        assert_eq!(game.world.winners, vec![0]);
+    }
+}
@@ -1,4 +1,7 @@
-use super::Position;
+use std::collections::VecDeque;
+use std::ops::{Index, IndexMut};
+
+use super::{Position, Pos, Topology, Orientation};
 
 #[derive(Debug, Clone)]
 pub struct Grid<T> {
@@ -11,8 +14,8 @@ impl<T: Clone> Grid<T> {
     pub fn from_elem(elem: T, rows: usize, cols: usize) -> Grid<T> {
         let size = rows*cols;
         Grid {
-            rows: rows, 
-            cols: cols,
+            rows,
+            cols,
             data: vec![elem; size]
         }
     }
@@ -23,6 +26,11 @@ impl<T> Grid<T> {
         debug_assert!(pos.row() < self.rows && pos.col() < self.cols);
         self.cols * pos.row() + pos.col()
     }
+    /// Position of the cell with index `i`, the inverse of `index`.
+    pub fn position(&self, i: usize) -> Pos {
+        debug_assert!(i < self.data.len());
+        Pos::new(i / self.cols, i % self.cols)
+    }
     pub fn set<P: Position>(&mut self, pos: P, value:T ) {
         let i = self.index(pos);
         self.data[i] = value;
@@ -31,11 +39,130 @@ impl<T> Grid<T> {
         let i = self.index(pos);
         &self.data[i]
     }
+    pub fn get_mut<P: Position>(&mut self, pos: P) -> &mut T {
+        let i = self.index(pos);
+        &mut self.data[i]
+    }
     pub fn rows(&self) -> usize {
         self.rows
     }
     pub fn cols(&self) -> usize {
         self.cols
     }
+    /// The topology of a grid of this size.
+    pub fn topology(&self, wrap: bool) -> Topology {
+        Topology { rows: self.rows, cols: self.cols, wrap }
+    }
+    /// All cells in row-major order, together with their position.
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &T)> {
+        let cols = self.cols;
+        self.data.iter().enumerate().map(move |(i, value)| (Pos::new(i / cols, i % cols), value))
+    }
+    /// All cells in row-major order, together with their position.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Pos, &mut T)> {
+        let cols = self.cols;
+        self.data.iter_mut().enumerate().map(move |(i, value)| (Pos::new(i / cols, i % cols), value))
+    }
+    /// New grid of the same size, with `f` applied to every cell.
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(f).collect()
+        }
+    }
+    /// Neighbours of `pos` and the direction to reach them.
+    pub fn neighbours<P: Position>(&self, pos: P, topology: Topology) -> impl Iterator<Item = (Orientation, Pos)> {
+        debug_assert!(topology.rows == self.rows && topology.cols == self.cols);
+        topology.neighbours(pos)
+    }
+    /// The region reachable from `start` by moving through cells that are `passable`. `start`
+    /// itself always belongs to the region.
+    pub fn flood_fill<P: Position, F: Fn(&T) -> bool>(&self, start: P, topology: Topology, passable: F) -> Grid<bool> {
+        let distances = self.distances(&[Pos::from_position(start)], topology, passable);
+        distances.map(|d| d.is_some())
+    }
+    /// Number of steps from the nearest of `starts` to every cell, moving through cells that are
+    /// `passable`. `None` for cells that can't be reached.
+    pub fn distances<F: Fn(&T) -> bool>(&self, starts: &[Pos], topology: Topology, passable: F) -> Grid<Option<usize>> {
+        debug_assert!(topology.rows == self.rows && topology.cols == self.cols);
+        let mut distances = Grid::from_elem(None, self.rows, self.cols);
+        let mut queue = VecDeque::new();
+        for start in starts {
+            distances.set(*start, Some(0));
+            queue.push_back(*start);
+        }
+        while let Some(pos) = queue.pop_front() {
+            let distance = distances[pos].unwrap_or(0);
+            for (_, next) in topology.neighbours(pos) {
+                if distances[next].is_none() && passable(&self[next]) {
+                    distances.set(next, Some(distance+1));
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
 }
 
+impl<T, P: Position> Index<P> for Grid<T> {
+    type Output = T;
+    fn index(&self, pos: P) -> &T {
+        self.get(pos)
+    }
+}
+
+impl<T, P: Position> IndexMut<P> for Grid<T> {
+    fn index_mut(&mut self, pos: P) -> &mut T {
+        self.get_mut(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3x4 grid with a wall of `false` cells in column 1, except in the last row.
+    fn maze() -> Grid<bool> {
+        let mut grid = Grid::from_elem(true, 3, 4);
+        grid[(0, 1)] = false;
+        grid[(1, 1)] = false;
+        grid
+    }
+
+    #[test]
+    fn cells_are_row_major() {
+        let grid = maze();
+        for i in 0..12 {
+            assert_eq!(grid.index(grid.position(i)), i);
+        }
+        assert_eq!(grid.position(5), Pos::new(1, 1));
+        let positions: Vec<Pos> = grid.iter().map(|(pos, _)| pos).take(5).collect();
+        assert_eq!(positions, vec![Pos::new(0, 0), Pos::new(0, 1), Pos::new(0, 2), Pos::new(0, 3), Pos::new(1, 0)]);
+        let counts = grid.map(|passable| *passable as usize);
+        assert_eq!(counts.iter().map(|(_, n)| n).sum::<usize>(), 10);
+    }
+
+    #[test]
+    fn distances_go_around_obstacles() {
+        let grid = maze();
+        let distances = grid.distances(&[Pos::new(0, 0)], grid.topology(false), |passable| *passable);
+        assert_eq!(distances[(0, 1)], None);
+        assert_eq!(distances[(2, 1)], Some(3));
+        assert_eq!(distances[(0, 2)], Some(6));
+        // over the edge of a torus, the wall is no obstacle
+        let distances = grid.distances(&[Pos::new(0, 0)], grid.topology(true), |passable| *passable);
+        assert_eq!(distances[(0, 2)], Some(2));
+    }
+
+    #[test]
+    fn flood_fill_stays_in_region() {
+        let mut grid = maze();
+        grid[(2, 1)] = false;
+        let region = grid.flood_fill((0, 0), grid.topology(false), |passable| *passable);
+        let cells: Vec<Pos> = region.iter().filter(|(_, reachable)| **reachable).map(|(pos, _)| pos).collect();
+        assert_eq!(cells, vec![Pos::new(0, 0), Pos::new(1, 0), Pos::new(2, 0)]);
+        let neighbours: Vec<Pos> = grid.neighbours((0, 0), grid.topology(false)).map(|(_, pos)| pos).collect();
+        assert_eq!(neighbours, vec![Pos::new(1, 0), Pos::new(0, 1)]);
+    }
+}
@@ -1,10 +1,13 @@
 mod cell;
 mod grid;
+mod topology;
 mod orientation;
 mod position;
 mod snake;
 mod world;
 mod game;
+mod zobrist;
+mod validation;
 
 pub use cell::*;
 pub use orientation::*;
@@ -13,4 +16,6 @@ pub use snake::*;
 pub use world::*;
 pub use game::*;
 pub use grid::*;
+pub use topology::*;
+pub use validation::*;
 
@@ -1,6 +1,6 @@
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Orientation {
     Up,
     Down,
@@ -8,6 +8,34 @@ pub enum Orientation {
     Right
 }
 
+impl Orientation {
+    /// All four orientations.
+    pub fn all() -> [Orientation; 4] {
+        [Orientation::Up, Orientation::Down, Orientation::Left, Orientation::Right]
+    }
+    pub fn opposite(self) -> Orientation {
+        match self {
+            Orientation::Up => Orientation::Down,
+            Orientation::Down => Orientation::Up,
+            Orientation::Left => Orientation::Right,
+            Orientation::Right => Orientation::Left
+        }
+    }
+    /// The orientation after a right turn.
+    pub fn clockwise(self) -> Orientation {
+        match self {
+            Orientation::Up => Orientation::Right,
+            Orientation::Right => Orientation::Down,
+            Orientation::Down => Orientation::Left,
+            Orientation::Left => Orientation::Up
+        }
+    }
+    /// The orientation after a left turn.
+    pub fn counter_clockwise(self) -> Orientation {
+        self.clockwise().opposite()
+    }
+}
+
 impl fmt::Display for Orientation {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
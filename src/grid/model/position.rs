@@ -1,3 +1,8 @@
+use std::fmt;
+use std::ops::{Add, Sub, Neg, Mul};
+
+use super::{Orientation, Topology};
+
 pub trait Position {
     fn row(&self) -> usize;
     fn col(&self) -> usize;
@@ -9,9 +14,148 @@ pub fn tuple_from_position<P: Position>(other: P) -> (usize, usize) {
 impl Position for (usize, usize) {
     fn row(&self) -> usize {
         self.0
-    } 
+    }
     fn col(&self) -> usize {
         self.1
     }
 }
 
+/// A cell on the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Pos {
+    pub row: usize,
+    pub col: usize
+}
+
+/// A displacement between two cells, rows grow downwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Vector {
+    pub row: isize,
+    pub col: isize
+}
+
+impl Pos {
+    pub fn new(row: usize, col: usize) -> Pos {
+        Pos { row, col }
+    }
+    pub fn from_position<P: Position>(pos: P) -> Pos {
+        Pos { row: pos.row(), col: pos.col() }
+    }
+    /// The neighbouring cell in `direction`, `None` if it's behind a wall.
+    pub fn step(self, direction: Orientation, topology: Topology) -> Option<Pos> {
+        topology.step(self, direction)
+    }
+    /// Moves by `vector`, wrapping around the edges of a torus. `None` if the target is behind a
+    /// wall.
+    pub fn offset(self, vector: Vector, topology: Topology) -> Option<Pos> {
+        let row = self.row as isize + vector.row;
+        let col = self.col as isize + vector.col;
+        if topology.wrap {
+            return Some(Pos::new(row.rem_euclid(topology.rows as isize) as usize, col.rem_euclid(topology.cols as isize) as usize));
+        }
+        if row < 0 || col < 0 || row >= topology.rows as isize || col >= topology.cols as isize {
+            return None;
+        }
+        Some(Pos::new(row as usize, col as usize))
+    }
+    /// Number of steps to `other`, if nothing wraps around.
+    pub fn manhattan_distance(self, other: Pos) -> usize {
+        (other - self).manhattan_length()
+    }
+    /// Number of steps to `other` on a torus of the given size.
+    pub fn toroidal_distance(self, other: Pos, rows: usize, cols: usize) -> usize {
+        Topology::torus(rows, cols).vector(self, other).manhattan_length()
+    }
+}
+
+impl Position for Pos {
+    fn row(&self) -> usize {
+        self.row
+    }
+    fn col(&self) -> usize {
+        self.col
+    }
+}
+
+impl From<(usize, usize)> for Pos {
+    fn from((row, col): (usize, usize)) -> Pos {
+        Pos { row, col }
+    }
+}
+
+impl From<Pos> for (usize, usize) {
+    fn from(pos: Pos) -> (usize, usize) {
+        (pos.row, pos.col)
+    }
+}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.row, self.col)
+    }
+}
+
+impl Vector {
+    pub fn new(row: isize, col: isize) -> Vector {
+        Vector { row, col }
+    }
+    pub fn manhattan_length(self) -> usize {
+        self.row.unsigned_abs() + self.col.unsigned_abs()
+    }
+    /// The vector turned by 90 degrees, `Up` becomes `Right`.
+    pub fn clockwise(self) -> Vector {
+        Vector::new(self.col, -self.row)
+    }
+}
+
+impl From<Orientation> for Vector {
+    fn from(direction: Orientation) -> Vector {
+        match direction {
+            Orientation::Up => Vector::new(-1, 0),
+            Orientation::Down => Vector::new(1, 0),
+            Orientation::Left => Vector::new(0, -1),
+            Orientation::Right => Vector::new(0, 1)
+        }
+    }
+}
+
+impl From<Pos> for Vector {
+    fn from(pos: Pos) -> Vector {
+        Vector::new(pos.row as isize, pos.col as isize)
+    }
+}
+
+impl Sub for Pos {
+    type Output = Vector;
+    fn sub(self, other: Pos) -> Vector {
+        Vector::new(self.row as isize - other.row as isize, self.col as isize - other.col as isize)
+    }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+    fn add(self, other: Vector) -> Vector {
+        Vector::new(self.row + other.row, self.col + other.col)
+    }
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+    fn sub(self, other: Vector) -> Vector {
+        Vector::new(self.row - other.row, self.col - other.col)
+    }
+}
+
+impl Neg for Vector {
+    type Output = Vector;
+    fn neg(self) -> Vector {
+        Vector::new(-self.row, -self.col)
+    }
+}
+
+impl Mul<isize> for Vector {
+    type Output = Vector;
+    fn mul(self, factor: isize) -> Vector {
+        Vector::new(self.row * factor, self.col * factor)
+    }
+}
@@ -1,24 +1,28 @@
 use std::fmt;
 
-use super::Position;
-
-type Pos = (usize, usize);
+use super::Pos;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Snake {
     pub head: Pos,
     pub tail: Pos,
     pub pending_growth: usize,
-    pub length: usize
+    pub length: usize,
+    pub alive: bool,
+    /// An opponent hidden by the fog of war in `World::observed_by`: it never moves, dies, wins
+    /// or loses, but keeps the game going like a living snake.
+    pub ghost: bool
 }
 
 impl Snake {
     pub fn new(head: Pos, tail: Pos) -> Snake {
         Snake {
-            head: head,
-            tail: tail,
+            head,
+            tail,
             pending_growth: 0,
-            length: 2
+            length: 2,
+            alive: true,
+            ghost: false
         }
     }
 }
@@ -26,7 +30,7 @@ impl Snake {
 impl fmt::Display for Snake{
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Snake(({},{}), ({},{}))", 
-               self.head.row(), self.head.col(), self.tail.row(), self.tail.col())
+               self.head.row, self.head.col, self.tail.row, self.tail.col)
     }
 }
 
@@ -0,0 +1,114 @@
+use super::{Position, Pos, Vector, Orientation};
+
+/// Shape of a grid: its size and whether moving over an edge wraps around to the opposite edge
+/// or is blocked by a wall.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Topology {
+    pub rows: usize,
+    pub cols: usize,
+    pub wrap: bool
+}
+
+impl Topology {
+    /// Grid surrounded by walls.
+    pub fn bounded(rows: usize, cols: usize) -> Topology {
+        Topology { rows, cols, wrap: false }
+    }
+    /// Grid whose edges wrap around.
+    pub fn torus(rows: usize, cols: usize) -> Topology {
+        Topology { rows, cols, wrap: true }
+    }
+    /// The neighbouring cell of `pos` in `direction`, `None` if it's behind a wall.
+    pub fn step<P: Position>(&self, pos: P, direction: Orientation) -> Option<Pos> {
+        let (row, col) = (pos.row(), pos.col());
+        debug_assert!(row < self.rows && col < self.cols);
+        let (row, col) = match direction {
+            Orientation::Up if row > 0 => (row-1, col),
+            Orientation::Up if self.wrap => (self.rows-1, col),
+            Orientation::Down if row+1 < self.rows => (row+1, col),
+            Orientation::Down if self.wrap => (0, col),
+            Orientation::Left if col > 0 => (row, col-1),
+            Orientation::Left if self.wrap => (row, self.cols-1),
+            Orientation::Right if col+1 < self.cols => (row, col+1),
+            Orientation::Right if self.wrap => (row, 0),
+            _ => return None
+        };
+        Some(Pos::new(row, col))
+    }
+    /// The up to four neighbours of `pos`, together with the direction to reach them. Every cell
+    /// is listed once, even on a torus only one or two cells wide.
+    pub fn neighbours<P: Position>(&self, pos: P) -> impl Iterator<Item = (Orientation, Pos)> {
+        let topology = *self;
+        let pos = Pos::from_position(pos);
+        IntoIterator::into_iter(Orientation::all()).filter_map(move |d| {
+            let next = topology.step(pos, d)?;
+            // up and left come first, down and right might wrap around to the same cell
+            let seen = matches!(d, Orientation::Down | Orientation::Right) && topology.step(pos, d.opposite()) == Some(next);
+            if next == pos || seen {
+                return None;
+            }
+            Some((d, next))
+        })
+    }
+    /// Shortest displacement from `from` to `to`. On a torus, it might go over an edge.
+    pub fn vector(&self, from: Pos, to: Pos) -> Vector {
+        let mut v = to - from;
+        if self.wrap {
+            let (rows, cols) = (self.rows as isize, self.cols as isize);
+            if v.row.abs() * 2 > rows {
+                v.row -= v.row.signum() * rows;
+            }
+            if v.col.abs() * 2 > cols {
+                v.col -= v.col.signum() * cols;
+            }
+        }
+        v
+    }
+    /// Number of steps from `from` to `to` on an otherwise empty grid.
+    pub fn distance(&self, from: Pos, to: Pos) -> usize {
+        self.vector(from, to).manhattan_length()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn neighbours(topology: Topology, pos: Pos) -> Vec<Pos> {
+        let mut cells: Vec<Pos> = topology.neighbours(pos).map(|(_, next)| next).collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn walls_block_steps() {
+        let topology = Topology::bounded(3, 4);
+        assert_eq!(topology.step(Pos::new(0, 0), Orientation::Up), None);
+        assert_eq!(topology.step(Pos::new(0, 3), Orientation::Right), None);
+        assert_eq!(topology.step(Pos::new(1, 1), Orientation::Down), Some(Pos::new(2, 1)));
+        assert_eq!(neighbours(topology, Pos::new(0, 0)), vec![Pos::new(0, 1), Pos::new(1, 0)]);
+        assert_eq!(neighbours(topology, Pos::new(1, 1)).len(), 4);
+    }
+
+    #[test]
+    fn torus_wraps_around() {
+        let topology = Topology::torus(3, 4);
+        assert_eq!(topology.step(Pos::new(0, 0), Orientation::Up), Some(Pos::new(2, 0)));
+        assert_eq!(topology.step(Pos::new(0, 3), Orientation::Right), Some(Pos::new(0, 0)));
+        assert_eq!(neighbours(topology, Pos::new(0, 0)), vec![Pos::new(0, 1), Pos::new(0, 3), Pos::new(1, 0), Pos::new(2, 0)]);
+        assert_eq!(topology.vector(Pos::new(0, 0), Pos::new(2, 3)), Vector::new(-1, -1));
+        assert_eq!(topology.distance(Pos::new(0, 0), Pos::new(2, 3)), 2);
+        assert_eq!(Topology::bounded(3, 4).distance(Pos::new(0, 0), Pos::new(2, 3)), 5);
+    }
+
+    #[test]
+    fn narrow_torus_has_no_duplicate_neighbours() {
+        // up and down lead to the same row, left and right back to the cell itself
+        let topology = Topology::torus(2, 1);
+        assert_eq!(topology.neighbours(Pos::new(0, 0)).collect::<Vec<_>>(), vec![(Orientation::Up, Pos::new(1, 0))]);
+        let topology = Topology::torus(2, 2);
+        assert_eq!(neighbours(topology, Pos::new(1, 1)), vec![Pos::new(0, 1), Pos::new(1, 0)]);
+        let topology = Topology::torus(3, 2);
+        assert_eq!(neighbours(topology, Pos::new(0, 0)), vec![Pos::new(0, 1), Pos::new(1, 0), Pos::new(2, 0)]);
+    }
+}
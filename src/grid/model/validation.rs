@@ -0,0 +1,204 @@
+use std::fmt;
+
+use super::{World, Player, Cell, Pos};
+
+/// An inconsistency in a `World`, found by `World::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// head or tail of the snake lies outside of the grid
+    OutOfBounds(Player, Pos),
+    /// head, tail or a segment between them isn't a cell of the snake
+    NotSnakeCell(Player, Pos),
+    /// following the segments from the tail leaves the grid or never reaches the head
+    BrokenChain(Player, Pos),
+    /// number of segments from tail to head doesn't match `Snake::length`
+    WrongLength { player: Player, length: usize, segments: usize },
+    /// cells of the snake that aren't part of the chain from tail to head
+    StraySegments { player: Player, cells: usize, segments: usize },
+    /// a snake cell refers to a player that doesn't exist
+    UnknownSnake(usize, Pos),
+    /// `available_snacks` doesn't match the number of food cells
+    SnackCount { available: usize, food_cells: usize },
+    /// the incrementally updated hash doesn't match the world
+    StaleHash { hash: u64, computed: u64 }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::OutOfBounds(s, pos) => write!(f, "snake {}: {} is outside of the grid", s, pos),
+            Violation::NotSnakeCell(s, pos) => write!(f, "snake {}: {} is not a cell of the snake", s, pos),
+            Violation::BrokenChain(s, pos) => write!(f, "snake {}: chain from tail to head breaks at {}", s, pos),
+            Violation::WrongLength { player, length, segments } =>
+                write!(f, "snake {}: length is {}, but there are {} segments from tail to head", player, length, segments),
+            Violation::StraySegments { player, cells, segments } =>
+                write!(f, "snake {}: {} cells on the grid, but only {} segments from tail to head", player, cells, segments),
+            Violation::UnknownSnake(s, pos) => write!(f, "{} belongs to unknown snake {}", pos, s),
+            Violation::SnackCount { available, food_cells } =>
+                write!(f, "{} available snacks, but {} food cells", available, food_cells),
+            Violation::StaleHash { hash, computed } => write!(f, "hash is {:016x}, but should be {:016x}", hash, computed)
+        }
+    }
+}
+
+impl World {
+    /// Checks the global consistency of the world: every snake is a chain of cells from its tail
+    /// to its head with the right length, there are no other snake cells, the snack count
+    /// matches the food on the grid and the hash is up to date.
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        let mut cells_per_snake = vec![0; self.snakes.len()];
+        let mut food_cells = 0;
+        for (pos, cell) in self.grid.iter() {
+            match cell {
+                Cell::Food(_) => food_cells += 1,
+                Cell::Snake(s, _) if *s < self.snakes.len() => cells_per_snake[*s] += 1,
+                Cell::Snake(s, _) => violations.push(Violation::UnknownSnake(*s, pos)),
+                _ => ()
+            }
+        }
+        for (s, cells) in cells_per_snake.iter().enumerate() {
+            // the body of dead snakes might be gone, ghosts are only partly visible
+            if (!self.snakes[s].alive && *cells == 0) || self.snakes[s].ghost {
+                continue;
+            }
+            if let Some(segments) = self.validate_chain(s, &mut violations) {
+                if segments != self.snakes[s].length {
+                    violations.push(Violation::WrongLength { player: s, length: self.snakes[s].length, segments });
+                }
+                if segments != *cells {
+                    violations.push(Violation::StraySegments { player: s, cells: *cells, segments });
+                }
+            }
+        }
+        if food_cells != self.available_snacks() {
+            violations.push(Violation::SnackCount { available: self.available_snacks(), food_cells });
+        }
+        if self.hash() != self.compute_hash() {
+            violations.push(Violation::StaleHash { hash: self.hash(), computed: self.compute_hash() });
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+    /// Walks snake `s` from tail to head, returns the number of segments if the chain is intact.
+    fn validate_chain(&self, s: Player, violations: &mut Vec<Violation>) -> Option<usize> {
+        let snake = self.snakes[s];
+        let mut intact = true;
+        for pos in [snake.head, snake.tail].iter() {
+            if pos.row >= self.grid.rows() || pos.col >= self.grid.cols() {
+                violations.push(Violation::OutOfBounds(s, *pos));
+                intact = false;
+            } else if !matches!(self.grid.get(*pos), Cell::Snake(id, _) if *id == s) {
+                violations.push(Violation::NotSnakeCell(s, *pos));
+                intact = false;
+            }
+        }
+        if !intact {
+            return None;
+        }
+        let mut pos = snake.tail;
+        let mut segments = 1;
+        while pos != snake.head {
+            // a chain longer than the grid has to contain a loop
+            if segments > self.grid.rows() * self.grid.cols() {
+                violations.push(Violation::BrokenChain(s, pos));
+                return None;
+            }
+            pos = match self.step(pos, self.snake_direction(pos)) {
+                Some(next) => next,
+                None => {
+                    violations.push(Violation::BrokenChain(s, pos));
+                    return None;
+                }
+            };
+            if !matches!(self.grid.get(pos), Cell::Snake(id, _) if *id == s) {
+                violations.push(Violation::NotSnakeCell(s, pos));
+                return None;
+            }
+            segments += 1;
+        }
+        Some(segments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Orientation;
+
+    /// 6x6 world with walls and a snake heading down from the top edge, its tail at (0, 2).
+    fn world() -> World {
+        let mut world = World::with_seed(6, 6, 1);
+        world.wall_collision = true;
+        world.add_snake((1, 2), Orientation::Down).unwrap();
+        assert_eq!(world.validate(), Ok(()));
+        world
+    }
+
+    fn violations(mut world: World) -> Vec<Violation> {
+        world.rehash();
+        world.validate().unwrap_err()
+    }
+
+    #[test]
+    fn head_outside_of_the_grid() {
+        let mut world = world();
+        world.snakes[0].head = Pos::new(9, 9);
+        assert_eq!(violations(world), vec![Violation::OutOfBounds(0, Pos::new(9, 9))]);
+    }
+
+    #[test]
+    fn head_that_is_no_snake_cell() {
+        let mut world = world();
+        world.grid.set((1, 2), Cell::Empty);
+        assert_eq!(violations(world), vec![Violation::NotSnakeCell(0, Pos::new(1, 2))]);
+    }
+
+    #[test]
+    fn chain_into_the_wall() {
+        let mut world = world();
+        world.grid.set((0, 2), Cell::Snake(0, Orientation::Up));
+        assert_eq!(violations(world), vec![Violation::BrokenChain(0, Pos::new(0, 2))]);
+    }
+
+    #[test]
+    fn wrong_length() {
+        let mut world = world();
+        world.snakes[0].length = 3;
+        assert_eq!(violations(world), vec![Violation::WrongLength { player: 0, length: 3, segments: 2 }]);
+    }
+
+    #[test]
+    fn stray_segment() {
+        let mut world = world();
+        world.grid.set((4, 4), Cell::Snake(0, Orientation::Down));
+        assert_eq!(violations(world), vec![Violation::StraySegments { player: 0, cells: 3, segments: 2 }]);
+    }
+
+    #[test]
+    fn unknown_snake() {
+        let mut world = world();
+        world.grid.set((4, 4), Cell::Snake(7, Orientation::Down));
+        assert_eq!(violations(world), vec![Violation::UnknownSnake(7, Pos::new(4, 4))]);
+    }
+
+    #[test]
+    fn snack_count_mismatch() {
+        let mut world = world();
+        world.place_snack((4, 4), 1).unwrap();
+        world.grid.set((4, 4), Cell::Empty);
+        assert_eq!(violations(world), vec![Violation::SnackCount { available: 1, food_cells: 0 }]);
+    }
+
+    #[test]
+    fn stale_hash() {
+        let mut world = world();
+        world.grid.set((4, 4), Cell::Stone);
+        let violations = world.validate().unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert!(matches!(violations[0], Violation::StaleHash { hash, computed } if hash == world.hash() && computed != hash));
+    }
+}
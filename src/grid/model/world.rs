@@ -1,21 +1,50 @@
 use std::mem;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
 
-use super::{Grid, Cell, Snake, tuple_from_position, Position, Orientation};
+use super::{Grid, Cell, Snake, Position, Pos, Vector, Orientation, Topology};
+use super::zobrist;
 
 pub type Player = usize;
 
-#[derive(Clone)]
+/// A fresh seed for `World::with_seed` or `World::reseed`.
+pub fn random_seed() -> u64 {
+    // u32 draws only: after an odd number of u32 draws, `BlockRng::next_u64` of rand_core 0.3
+    // (block.rs, line 192) reads a u64 through a pointer that is only 4-byte aligned. That is
+    // undefined behaviour and debug builds panic with "misaligned pointer dereference".
+    let mut rng = rand::thread_rng();
+    (u64::from(rng.gen::<u32>()) << 32) | u64::from(rng.gen::<u32>())
+}
+
+#[derive(Debug, Clone)]
 pub enum GameEvent {
     Collision(Player, (isize, isize)), // position of collision
-    FoodConsumed(Player, usize) // growth value
+    FoodConsumed(Player, usize), // growth value
+    SnakeDied(Player),
+    BodyTurnedToFood(Player, usize), // number of food cells
+    SnakeCut(Player, Player, usize) // biting snake, bitten snake, number of severed segments
+}
+
+/// What the severed part of a cut snake turns into, see `World::snake_cutting`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Remains {
+    Food(usize), // growth value per segment
+    Stones
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    Classic,
+    /// snakes never shrink and leave a permanent trail, there is no food and the last snake alive
+    /// wins
+    Tron
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TurnResult {
     GameOver,
-    Draw, // nobody wins, nobody loses
+    Draw, // the last snakes died together, only those that were out before are losers
     Ok // nothing bad happened, game goes on
 }
 
@@ -26,13 +55,33 @@ pub struct World {
     events: Vec<GameEvent>,
     available_snacks: usize,
     pub wall_collision: bool,
+    /// If set, a head hitting the body of another snake cuts it instead of colliding. The bitten
+    /// snake keeps the part between its head and the bite, the rest turns into `Remains`.
+    pub snake_cutting: Option<Remains>,
+    /// In tron mode tails never move and snakes leave a permanent trail
+    pub mode: GameMode,
+    /// If set in a multiplayer game, collided snakes are eliminated and each of their segments
+    /// becomes food with this growth value. The game goes on until one snake is left.
+    pub dead_snake_food: Option<usize>,
+    /// If set, every snake only sees the square of this side length around its head, see
+    /// `observed_by`.
+    pub fog_of_war: Option<usize>,
     pub turn_result: TurnResult,
     pub winners: Vec<usize>,
-    pub losers: Vec<usize>
+    pub losers: Vec<usize>,
+    seed: u64,
+    rng: SmallRng,
+    hash: u64
 }
 
 impl World {
+    /// Creates an empty world with a random seed.
     pub fn new(rows: usize, cols: usize) -> World {
+        World::with_seed(rows, cols, random_seed())
+    }
+    /// Creates an empty world whose random placements (snacks, stones) are fully determined by
+    /// `seed`.
+    pub fn with_seed(rows: usize, cols: usize, seed: u64) -> World {
         debug_assert!(2 <= rows);
         debug_assert!(1 <= cols);
         World {
@@ -41,41 +90,139 @@ impl World {
             events: Vec::new(),
             available_snacks: 0,
             wall_collision: false,
+            snake_cutting: None,
+            mode: GameMode::Classic,
+            dead_snake_food: None,
+            fog_of_war: None,
             turn_result: TurnResult::Ok,
             winners: Vec::new(),
-            losers: Vec::new()
+            losers: Vec::new(),
+            seed,
+            rng: SmallRng::seed_from_u64(seed),
+            hash: zobrist::snacks_key(0)
         }
     }
-    pub fn add_snake(&mut self, (head_row, head_col): (usize, usize), direction: Orientation) -> Result<(), ()> {
-        debug_assert!(head_row < self.grid.rows());
-        debug_assert!(head_col < self.grid.cols());
-        if direction == Orientation::Up && head_row >= self.grid.rows()-1 {
-            return Err(());
+    /// Rebuilds a world from a recorded grid and snakes, e.g. when loading a replay.
+    pub(crate) fn from_parts(grid: Grid<Cell>, snakes: Vec<Snake>, seed: u64) -> World {
+        let mut world = World::with_seed(grid.rows(), grid.cols(), seed);
+        world.available_snacks = grid.iter().filter(|(_, cell)| matches!(cell, Cell::Food(_))).count();
+        world.grid = grid;
+        world.snakes = snakes;
+        world.rehash();
+        world
+    }
+    /// The seed the random number generator was last (re)seeded with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Restarts the random number generator, all following random placements only depend on
+    /// `seed` and the moves of the players.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = SmallRng::seed_from_u64(seed);
+    }
+    /// Hash of the grid, the snakes and the number of available snacks. Two worlds with the same
+    /// state have the same hash, independent of how they got there. It is updated incrementally by
+    /// all methods of `World`, call `rehash` after modifying `grid` or `snakes` directly.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+    /// Computes the hash from scratch, without looking at the incrementally updated value.
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = zobrist::snacks_key(self.available_snacks);
+        for row in 0..self.grid.rows() {
+            for col in 0..self.grid.cols() {
+                hash ^= zobrist::cell_key(self.grid.index((row, col)), self.grid.get((row, col)));
+            }
         }
-        if direction == Orientation::Down && head_row <= 0 {
-            return Err(());
+        for (i, snake) in self.snakes.iter().enumerate() {
+            hash ^= zobrist::snake_key(i, snake);
         }
-        if direction == Orientation::Left && head_col >= self.grid.cols()-1 {
-            return Err(());
+        hash
+    }
+    pub fn rehash(&mut self) {
+        self.hash = self.compute_hash();
+    }
+    /// Whether snake `player` sees `pos` under `fog_of_war`: it lies in the square around the
+    /// head.
+    pub fn is_visible(&self, player: Player, pos: Pos) -> bool {
+        match self.fog_of_war {
+            None => true,
+            Some(size) => {
+                let radius = (size / 2) as isize;
+                let v = self.topology().vector(self.snakes[player].head, pos);
+                v.row.abs() <= radius && v.col.abs() <= radius
+            }
         }
-        if direction == Orientation::Right && head_col <= 0{
-            return Err(());
+    }
+    /// The world as snake `player` sees it under `fog_of_war`, cells it can't see are empty. Its
+    /// own body stays whole. The other living snakes are ghosts in this world, their visible
+    /// segments are obstacles that never move. What can't be seen of them is scrubbed: their
+    /// length counts the visible segments only, and a hidden head or tail is moved to the origin.
+    pub fn observed_by(&self, player: Player) -> World {
+        let mut world = self.clone();
+        if self.fog_of_war.is_none() {
+            return world;
         }
-        let (tail_row, tail_col) = match direction {
-            Orientation::Up => (head_row+1, head_col),
-            Orientation::Down => (head_row-1, head_col),
-            Orientation::Left => (head_row, head_col+1),
-            Orientation::Right => (head_row, head_col-1)
-        };
-        if match self.grid.get((head_row, head_col)) {Cell::Empty => false, _ => true} {
+        for (pos, cell) in world.grid.iter_mut() {
+            let own = matches!(cell, Cell::Snake(s, _) if *s == player);
+            if !own && !self.is_visible(player, pos) {
+                *cell = Cell::Empty;
+            }
+        }
+        for s in 0..world.snakes.len() {
+            if s == player {
+                continue;
+            }
+            let mut snake = world.snakes[s];
+            if snake.alive {
+                snake.alive = false;
+                snake.ghost = true;
+            }
+            snake.length = world.grid.iter().filter(|(_, cell)| matches!(cell, Cell::Snake(id, _) if *id == s)).count();
+            snake.pending_growth = 0;
+            if !self.is_visible(player, snake.head) {
+                snake.head = Pos::new(0, 0);
+            }
+            if !self.is_visible(player, snake.tail) {
+                snake.tail = Pos::new(0, 0);
+            }
+            world.snakes[s] = snake;
+        }
+        world.available_snacks = world.grid.iter().filter(|(_, cell)| matches!(cell, Cell::Food(_))).count();
+        world.rehash();
+        world
+    }
+    fn set_cell<P: Position + Copy>(&mut self, pos: P, cell: Cell) {
+        let i = self.grid.index(pos);
+        self.hash ^= zobrist::cell_key(i, self.grid.get(pos)) ^ zobrist::cell_key(i, &cell);
+        self.grid.set(pos, cell);
+    }
+    fn set_snake(&mut self, s: Player, snake: Snake) {
+        self.hash ^= zobrist::snake_key(s, &self.snakes[s]) ^ zobrist::snake_key(s, &snake);
+        self.snakes[s] = snake;
+    }
+    fn set_available_snacks(&mut self, available_snacks: usize) {
+        self.hash ^= zobrist::snacks_key(self.available_snacks) ^ zobrist::snacks_key(available_snacks);
+        self.available_snacks = available_snacks;
+    }
+    pub fn add_snake<P: Position>(&mut self, head: P, direction: Orientation) -> Result<(), ()> {
+        let head = Pos::from_position(head);
+        debug_assert!(head.row < self.grid.rows());
+        debug_assert!(head.col < self.grid.cols());
+        // the tail lies behind the head, the snake must not start across an edge
+        let tail = head.offset(-Vector::from(direction), self.grid.topology(false)).ok_or(())?;
+        if !matches!(self.grid.get(head), Cell::Empty) {
             return Err(());
         }
-        if match self.grid.get((tail_row, tail_col)) {Cell::Empty => false, _ => true} {
+        if !matches!(self.grid.get(tail), Cell::Empty) {
             return Err(());
         }
-        self.snakes.push(Snake::new((head_row, head_col), (tail_row, tail_col)));
-        self.grid.set((head_row, head_col), Cell::Snake(self.snakes.len()-1, direction));
-        self.grid.set((tail_row, tail_col), Cell::Snake(self.snakes.len()-1, direction));
+        let snake = Snake::new(head, tail);
+        self.hash ^= zobrist::snake_key(self.snakes.len(), &snake);
+        self.snakes.push(snake);
+        self.set_cell(head, Cell::Snake(self.snakes.len()-1, direction));
+        self.set_cell(tail, Cell::Snake(self.snakes.len()-1, direction));
         Ok(())
     }
     pub fn available_snacks(&self) -> usize {
@@ -86,46 +233,93 @@ impl World {
     }
     pub fn snake_direction<P: Position + Copy>(&self, pos: P) -> Orientation {
         match self.grid.get(pos) {
-            Cell::Snake(_id, orientation) => orientation.clone(),
+            Cell::Snake(_id, orientation) => *orientation,
             cell_should_be_snake => panic!("{}, ({}, {}): should be snake cell", cell_should_be_snake, pos.row(), pos.col())
         }
     }
-    pub fn move_vector(&self, direction: Orientation) -> (isize, isize) {
-        let vertical = match direction {
-            Orientation::Up => -1,
-            Orientation::Down => 1,
-            _ => 0
+    /// The neighbouring cell of `pos` in `direction`, `None` if it's behind a wall.
+    pub fn step<P: Position>(&self, pos: P, direction: Orientation) -> Option<Pos> {
+        self.topology().step(pos, direction)
+    }
+    /// Walls block the edges of the grid if `wall_collision` is set, otherwise they wrap around.
+    pub fn topology(&self) -> Topology {
+        self.grid.topology(!self.wall_collision)
+    }
+    /// All cells of snake `s`, from tail to head.
+    pub fn snake_cells(&self, s: Player) -> Vec<Pos> {
+        let snake = self.snakes[s];
+        let mut cells = vec![snake.tail];
+        let mut pos = snake.tail;
+        while pos != snake.head {
+            pos = self.step(pos, self.snake_direction(pos)).expect("snake leaves the grid");
+            cells.push(pos);
+            debug_assert!(cells.len() <= snake.length);
+        }
+        cells
+    }
+    /// Takes snake `s` out of the game. Its body stays on the grid as an obstacle, unless
+    /// `dead_snake_food` is set: then every segment becomes food with that growth value. Returns
+    /// the number of segments that became food.
+    pub fn kill_snake(&mut self, s: Player) -> usize {
+        let mut snake = self.snakes[s];
+        snake.alive = false;
+        self.set_snake(s, snake);
+        let growth_value = match self.dead_snake_food {
+            Some(growth_value) => growth_value,
+            None => return 0
         };
-        let horizontal = match direction {
-            Orientation::Left => -1,
-            Orientation::Right => 1,
-            _ => 0
+        let cells = self.snake_cells(s);
+        for pos in &cells {
+            self.set_cell(*pos, Cell::Food(growth_value));
+        }
+        self.set_available_snacks(self.available_snacks + cells.len());
+        cells.len()
+    }
+    /// Cuts snake `s` at `pos`, which has to be a segment behind its head. `pos` becomes empty,
+    /// the segments behind it turn into `snake_cutting` remains. Returns the number of severed
+    /// segments, including `pos`.
+    fn cut_snake(&mut self, s: Player, pos: Pos) -> usize {
+        let cells = self.snake_cells(s);
+        let bite = cells.iter().position(|cell| *cell == pos).expect("bite outside of the snake");
+        debug_assert!(bite+1 < cells.len());
+        let remains = match self.snake_cutting {
+            Some(Remains::Food(growth_value)) => Cell::Food(growth_value),
+            _ => Cell::Stone
         };
-        (vertical, horizontal)
-        
+        for cell in &cells[..bite] {
+            self.set_cell(*cell, remains);
+        }
+        if let Cell::Food(_) = remains {
+            self.set_available_snacks(self.available_snacks + bite);
+        }
+        self.set_cell(pos, Cell::Empty);
+        let mut snake = self.snakes[s];
+        snake.tail = cells[bite+1];
+        snake.length -= bite+1;
+        self.set_snake(s, snake);
+        bite+1
+    }
+    /// Number of snakes still in the game, ghosts included.
+    pub fn alive_count(&self) -> usize {
+        self.snakes.iter().filter(|snake| snake.alive || snake.ghost).count()
     }
     pub fn place_snack_randomly(&mut self, growth_value: usize) -> Result<(), ()> {
-        let mut rng = rand::thread_rng();
         for _ in 0..100 {
-            let row = rng.gen_range(0, self.grid.rows());
-            let col = rng.gen_range(0, self.grid.cols());
-            match self.place_snack((row, col), growth_value) {
-                Ok(_) => return Ok(()),
-                _ => ()
-            };
+            let row = self.rng.gen_range(0, self.grid.rows());
+            let col = self.rng.gen_range(0, self.grid.cols());
+            if self.place_snack((row, col), growth_value).is_ok() {
+                return Ok(());
+            }
         }
-        return Err(());
+        Err(())
     }
     pub fn place_snack<P: Position + Copy>(&mut self, pos: P, growth_value: usize) -> Result<(), ()> {
-        if !match self.grid.get(pos) {
-            Cell::Empty => true,
-            _ => false
-        } {
+        if !matches!(self.grid.get(pos), Cell::Empty) {
             return Err(());
         }
         // place snack
-        self.grid.set(pos, Cell::Food(growth_value));
-        self.available_snacks += 1;
+        self.set_cell(pos, Cell::Food(growth_value));
+        self.set_available_snacks(self.available_snacks + 1);
         Ok(())
     }
     pub fn place_stones_randomly(&mut self, number_of_stones: usize) -> usize {
@@ -136,34 +330,31 @@ impl World {
                 Err(_) => 0
             }
         }
-        return placed;
+        placed
     }
     pub fn place_stone_randomly(&mut self) -> Result<(), ()> {
-        let mut rng = rand::thread_rng();
         for _ in 1..100 {
-            let row = rng.gen_range(0, self.grid.rows());
-            let col = rng.gen_range(0, self.grid.cols());
-            match self.place_stone((row, col)) {
-                Ok(_) => return Ok(()),
-                _ => ()
-            };
+            let row = self.rng.gen_range(0, self.grid.rows());
+            let col = self.rng.gen_range(0, self.grid.cols());
+            if self.place_stone((row, col)).is_ok() {
+                return Ok(());
+            }
         }
-        return Err(());
+        Err(())
     }
     pub fn place_stone<P: Position + Copy>(&mut self, pos: P) -> Result<(), ()> {
-        if match self.grid.get(pos) {
-            Cell::Empty => false,
-            _ => true
-        } {
+        if !matches!(self.grid.get(pos), Cell::Empty) {
             return Err(());
         }
-        self.grid.set(pos, Cell::Stone);
+        self.set_cell(pos, Cell::Stone);
         Ok(())
     }
-    pub fn advance(&mut self, directions: &Vec<Orientation>) -> Vec<GameEvent> {
+    pub fn advance(&mut self, directions: &[Orientation]) -> Vec<GameEvent> {
         self.events.clear();
-        for i in 0..self.snakes.len() {
-            self.advance_snake(i, directions[i]);
+        for (i, direction) in directions.iter().enumerate().take(self.snakes.len()) {
+            if self.snakes[i].alive {
+                self.advance_snake(i, *direction);
+            }
         }
         let mut events = Vec::new();
         mem::swap(&mut self.events, &mut events);
@@ -171,103 +362,295 @@ impl World {
     }
     fn check_direction(&self, s: usize, dir: Orientation) -> Orientation {
         let head_dir = self.snake_direction(self.snakes[s].head);
-        match (dir, head_dir) {
-            (Orientation::Left, Orientation::Right) => head_dir,
-            (Orientation::Right, Orientation::Left) => head_dir,
-            (Orientation::Up, Orientation::Down) => head_dir,
-            (Orientation::Down, Orientation::Up) => head_dir,
-            _ => dir
+        if dir == head_dir.opposite() {
+            head_dir
+        } else {
+            dir
         }
     }
     fn advance_snake(&mut self, s: Player, mut direction: Orientation) {
         let mut snake = self.snakes[s];
         direction = self.check_direction(s, direction);
-        let move_head = self.move_vector(direction);
-        let mut head_posi = ((snake.head.row() as isize + move_head.0), (snake.head.col() as isize + move_head.1));
-        if !self.wall_collision {
-            head_posi.0 = (self.grid.rows() as isize + head_posi.0) % self.grid.rows() as isize;
-            head_posi.1 = (self.grid.cols() as isize + head_posi.1) % self.grid.cols() as isize;
-        }
-        // check collision
-        if head_posi.0 < 0 || self.grid.rows() as isize <= head_posi.0 || head_posi.1 < 0 || self.grid.cols() as isize <= head_posi.1 {
-            // collision with a wall
-            self.events.push(GameEvent::Collision(s, head_posi));
-            return
-        }
-        let head_pos = (head_posi.0 as usize, head_posi.1 as usize);
-        match self.grid.get(head_pos) {
+        let head_pos = match snake.head.step(direction, self.topology()) {
+            Some(head_pos) => head_pos,
+            None => {
+                // collision with a wall, reported at the position behind the edge
+                let target = Vector::from(snake.head) + Vector::from(direction);
+                self.events.push(GameEvent::Collision(s, (target.row, target.col)));
+                return
+            }
+        };
+        match *self.grid.get(head_pos) {
             Cell::Food(growth_value) => {
-                self.events.push(GameEvent::FoodConsumed(s, *growth_value));
-                self.available_snacks -= 1; 
-                snake.pending_growth += *growth_value},
+                self.events.push(GameEvent::FoodConsumed(s, growth_value));
+                self.set_available_snacks(self.available_snacks - 1);
+                snake.pending_growth += growth_value},
             Cell::Empty => (),
+            Cell::Snake(os, _) if os != s && self.snakes[os].alive && !self.is_head(os, head_pos) && self.snake_cutting.is_some() => {
+                let severed = self.cut_snake(os, head_pos);
+                self.events.push(GameEvent::SnakeCut(s, os, severed));
+            },
             Cell::Snake(os,_) => {
-                self.events.push(GameEvent::Collision(s, head_posi));
+                self.events.push(GameEvent::Collision(s, (head_pos.row as isize, head_pos.col as isize)));
                 // if the other snake was already moved, and we collide with its head, it's also a
                 // collision for the other snake
-                if self.is_head(*os, head_pos) && *os < s {
-                    self.events.push(GameEvent::Collision(*os, (snake.head.0 as isize, snake.head.1 as isize)));
+                if self.is_head(os, head_pos) && os < s {
+                    self.events.push(GameEvent::Collision(os, (snake.head.row as isize, snake.head.col as isize)));
                 }
                 return;
             },
             Cell::Stone => {
-                self.events.push(GameEvent::Collision(s, head_posi));
+                self.events.push(GameEvent::Collision(s, (head_pos.row as isize, head_pos.col as isize)));
                 return;
             }
         };
         
         // update body segment with picked direction
-        self.grid.set(snake.head, Cell::Snake(s, direction));
+        self.set_cell(snake.head, Cell::Snake(s, direction));
         // update head position
         snake.head = head_pos;
-        self.grid.set(snake.head, Cell::Snake(s, direction));
+        self.set_cell(snake.head, Cell::Snake(s, direction));
 
         // update tail position if no longer growing
-        if snake.pending_growth == 0 {
-            let move_tail = self.move_vector(self.snake_direction(snake.tail));
-            // mod calculation for !wall_collision
-            let tail_pos = (((self.grid.rows() + snake.tail.row()) as isize + move_tail.0) as usize % self.grid.rows(), ((self.grid.rows() + snake.tail.col()) as isize + move_tail.1) as usize % self.grid.rows());
+        if self.mode == GameMode::Tron {
+            snake.pending_growth = snake.pending_growth.saturating_sub(1);
+            snake.length += 1;
+        } else if snake.pending_growth == 0 {
+            let tail_pos = snake.tail.step(self.snake_direction(snake.tail), self.topology()).expect("snake leaves the grid");
             let next_direction = self.snake_direction(tail_pos);
-            debug_assert!(match self.grid.get(tail_pos) {Cell::Snake(..) => true, _ => false});
-            self.grid.set(snake.tail, Cell::Empty);
+            debug_assert!(matches!(self.grid.get(tail_pos), Cell::Snake(..)));
+            self.set_cell(snake.tail, Cell::Empty);
             snake.tail = tail_pos;
-            self.grid.set(snake.tail, Cell::Snake(s, next_direction));
+            self.set_cell(snake.tail, Cell::Snake(s, next_direction));
         } else {
             snake.pending_growth -= 1;
             snake.length += 1;
         }
-        self.snakes[s] = snake;
-        debug_assert!(match self.grid.get(self.snakes[s].head) {Cell::Snake(ss, _) => *ss == s, _ => false});
+        self.set_snake(s, snake);
+        debug_assert!(matches!(self.grid.get(self.snakes[s].head), Cell::Snake(ss, _) if *ss == s));
         debug_assert!(self.is_head(s, self.snakes[s].head));
-        debug_assert!(match self.grid.get(self.snakes[s].tail) {Cell::Snake(ss, _) => *ss == s, _ => false});
+        debug_assert!(matches!(self.grid.get(self.snakes[s].tail), Cell::Snake(ss, _) if *ss == s));
         debug_assert!(self.is_tail(s, self.snakes[s].tail));
     }
     pub fn is_head<P: Position>(&self, snake: Player, pos: P) -> bool {
-        self.snakes[snake].head == tuple_from_position(pos)
+        self.snakes[snake].head == Pos::from_position(pos)
     }
     pub fn is_body<P: Position + Copy>(&self, snake: Player, pos: P) -> bool {
-        match self.grid.get(pos) {
-            Cell::Snake(s, _) => *s == snake && !self.is_head(snake, pos) && !self.is_tail(snake, pos),
-            _ => false
-        }
+        matches!(self.grid.get(pos), Cell::Snake(s, _) if *s == snake && !self.is_head(snake, pos) && !self.is_tail(snake, pos))
     }
     pub fn is_tail<P: Position>(&self, snake: Player, pos: P) -> bool {
-        self.snakes[snake].tail == tuple_from_position(pos)
+        self.snakes[snake].tail == Pos::from_position(pos)
     }
     pub fn set_head<P: Position>(&mut self, pos: P, direction: Orientation) {
         let s = 0;
-        self.grid.set(self.snakes[s].head, Cell::Empty);
-        self.snakes[s].head = tuple_from_position(pos);
-        self.grid.set(self.snakes[s].head, Cell::Snake(s, direction));
+        let mut snake = self.snakes[s];
+        self.set_cell(snake.head, Cell::Empty);
+        snake.head = Pos::from_position(pos);
+        self.set_snake(s, snake);
+        self.set_cell(snake.head, Cell::Snake(s, direction));
     }
-    pub fn set_body<P: Position>(&mut self, pos: P, direction: Orientation) {
+    pub fn set_body<P: Position + Copy>(&mut self, pos: P, direction: Orientation) {
         let s = 0;
-        self.grid.set(pos, Cell::Snake(s, direction));
+        self.set_cell(pos, Cell::Snake(s, direction));
     }
     pub fn set_tail<P: Position>(&mut self, pos: P, direction: Orientation) {
         let s = 0;
-        self.grid.set(self.snakes[s].tail, Cell::Empty);
-        self.snakes[s].tail = tuple_from_position(pos);
-        self.grid.set(self.snakes[s].tail, Cell::Snake(s, direction));
+        let mut snake = self.snakes[s];
+        self.set_cell(snake.tail, Cell::Empty);
+        snake.tail = Pos::from_position(pos);
+        self.set_snake(s, snake);
+        self.set_cell(snake.tail, Cell::Snake(s, direction));
     }
  }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10x10 world with walls, snake 0 in the top left and snake 1 in the bottom right corner, both
+    /// heading down, and food next to each of them.
+    fn two_corners() -> World {
+        let mut world = World::with_seed(10, 10, 1);
+        world.wall_collision = true;
+        world.add_snake((1, 1), Orientation::Down).unwrap();
+        world.add_snake((8, 8), Orientation::Down).unwrap();
+        world.grid.set((2, 2), Cell::Food(1));
+        world.grid.set((9, 9), Cell::Food(1));
+        world.available_snacks = 2;
+        world.rehash();
+        world
+    }
+
+    #[test]
+    fn killed_snakes_stay_or_turn_into_food() {
+        let mut world = two_corners();
+        assert_eq!(world.kill_snake(0), 0);
+        assert!(!world.snakes[0].alive);
+        assert!(matches!(world.grid[(1, 1)], Cell::Snake(0, _)));
+        world.dead_snake_food = Some(3);
+        assert_eq!(world.kill_snake(1), 2);
+        assert!(!world.snakes[1].alive);
+        assert!(matches!(world.grid[(7, 8)], Cell::Food(3)) && matches!(world.grid[(8, 8)], Cell::Food(3)));
+        assert_eq!(world.available_snacks(), 4);
+        assert_eq!(world.hash(), world.compute_hash());
+    }
+
+    #[test]
+    fn random_seeds_after_odd_draws() {
+        // leaves thread_rng in the middle of a u64, see `random_seed`
+        rand::thread_rng().gen::<u32>();
+        assert_ne!(random_seed(), random_seed());
+    }
+
+    #[test]
+    fn fog_of_war_hides_the_far_away() {
+        let mut world = two_corners();
+        assert_eq!(world.observed_by(0).hash(), world.hash());
+        world.fog_of_war = Some(5);
+        assert!(world.is_visible(0, Pos::new(3, 3)));
+        assert!(!world.is_visible(0, Pos::new(4, 1)));
+        assert!(!world.is_visible(0, Pos::new(9, 9)));
+        let observed = world.observed_by(0);
+        assert!(matches!(observed.grid[(2, 2)], Cell::Food(1)));
+        assert!(matches!(observed.grid[(9, 9)], Cell::Empty));
+        assert!(matches!(observed.grid[(8, 8)], Cell::Empty));
+        assert_eq!(observed.available_snacks(), 1);
+        assert!(observed.snakes[0].alive && !observed.snakes[0].ghost);
+        assert!(observed.snakes[1].ghost);
+        assert_eq!(observed.alive_count(), 2);
+        // nothing is left of the hidden snake
+        let hidden = observed.snakes[1];
+        assert_eq!((hidden.head, hidden.tail, hidden.length, hidden.pending_growth), (Pos::new(0, 0), Pos::new(0, 0), 0, 0));
+        assert_eq!(observed.validate(), Ok(()));
+    }
+
+    #[test]
+    fn fog_of_war_wraps_around_a_torus() {
+        let mut world = two_corners();
+        world.wall_collision = false;
+        world.fog_of_war = Some(5);
+        // two steps up and left from (1, 1), over the edges
+        assert!(world.is_visible(0, Pos::new(9, 9)));
+        let observed = world.observed_by(0);
+        assert!(matches!(observed.grid[(9, 9)], Cell::Food(1)));
+        assert!(matches!(observed.grid[(8, 8)], Cell::Empty));
+        // the tail of snake 0 is two rows, but three columns away from the head of snake 1
+        assert!(matches!(world.observed_by(1).grid[(0, 1)], Cell::Empty));
+        assert!(matches!(world.observed_by(0).grid[(0, 1)], Cell::Snake(0, _)));
+    }
+
+    /// Adds a snake along `cells`, from its tail to its head.
+    fn add_long_snake(world: &mut World, cells: &[(usize, usize)], heading: Orientation) {
+        let s = world.snakes.len();
+        let topology = world.topology();
+        for (i, cell) in cells.iter().enumerate() {
+            let direction = match cells.get(i+1) {
+                Some(next) => *Orientation::all().iter().find(|d| topology.step(*cell, **d) == Some(Pos::new(next.0, next.1))).unwrap(),
+                None => heading
+            };
+            world.grid.set(*cell, Cell::Snake(s, direction));
+        }
+        let (tail, head) = (cells[0], cells[cells.len()-1]);
+        let mut snake = Snake::new(Pos::new(head.0, head.1), Pos::new(tail.0, tail.1));
+        snake.length = cells.len();
+        world.snakes.push(snake);
+        world.rehash();
+    }
+
+    /// 8x8 world with walls and cutting, snake 0 heads right towards the body of snake 1, which
+    /// heads down in column 2.
+    fn about_to_bite(remains: Remains) -> World {
+        let mut world = World::with_seed(8, 8, 1);
+        world.wall_collision = true;
+        world.snake_cutting = Some(remains);
+        add_long_snake(&mut world, &[(4, 0), (4, 1)], Orientation::Right);
+        add_long_snake(&mut world, &[(1, 2), (2, 2), (3, 2), (4, 2), (5, 2), (6, 2)], Orientation::Down);
+        assert_eq!(world.validate(), Ok(()));
+        world
+    }
+
+    fn snake_cut(events: &[GameEvent]) -> Option<(Player, Player, usize)> {
+        events.iter().find_map(|e| match e {
+            GameEvent::SnakeCut(s, os, severed) => Some((*s, *os, *severed)),
+            _ => None
+        })
+    }
+
+    #[test]
+    fn cut_tail_turns_into_food() {
+        let mut world = about_to_bite(Remains::Food(1));
+        let events = world.advance(&[Orientation::Right, Orientation::Down]);
+        // the three segments behind the bite turn into food, the bitten one is taken by snake 0
+        assert_eq!(snake_cut(&events), Some((0, 1, 4)));
+        assert!(!events.iter().any(|e| matches!(e, GameEvent::Collision(..))));
+        for row in 1..4 {
+            assert!(matches!(world.grid[(row, 2)], Cell::Food(1)));
+        }
+        assert_eq!(world.available_snacks(), 3);
+        assert_eq!(world.snake_cells(0), vec![Pos::new(4, 1), Pos::new(4, 2)]);
+        assert_eq!(world.snake_cells(1), vec![Pos::new(6, 2), Pos::new(7, 2)]);
+        assert_eq!(world.snakes[1].length, 2);
+        assert!(world.snakes[0].alive && world.snakes[1].alive);
+        assert_eq!(world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn cut_tail_turns_into_stones() {
+        let mut world = about_to_bite(Remains::Stones);
+        world.advance(&[Orientation::Right, Orientation::Down]);
+        for row in 1..4 {
+            assert!(matches!(world.grid[(row, 2)], Cell::Stone));
+        }
+        assert_eq!(world.available_snacks(), 0);
+        assert_eq!(world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn biting_the_tail_cuts_one_segment() {
+        let mut world = World::with_seed(8, 8, 1);
+        world.wall_collision = true;
+        world.snake_cutting = Some(Remains::Food(1));
+        add_long_snake(&mut world, &[(1, 0), (1, 1)], Orientation::Right);
+        add_long_snake(&mut world, &[(1, 2), (2, 2), (3, 2)], Orientation::Down);
+        let events = world.advance(&[Orientation::Right, Orientation::Down]);
+        assert_eq!(snake_cut(&events), Some((0, 1, 1)));
+        assert_eq!(world.available_snacks(), 0);
+        assert_eq!(world.snake_cells(0), vec![Pos::new(1, 1), Pos::new(1, 2)]);
+        assert_eq!(world.snake_cells(1), vec![Pos::new(3, 2), Pos::new(4, 2)]);
+        assert_eq!(world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn biting_a_tail_that_already_moved() {
+        // snake 0 moves first, then snake 1 bites where its tail is now
+        let mut world = World::with_seed(8, 8, 1);
+        world.wall_collision = true;
+        world.snake_cutting = Some(Remains::Food(1));
+        add_long_snake(&mut world, &[(1, 2), (2, 2), (3, 2)], Orientation::Down);
+        add_long_snake(&mut world, &[(2, 0), (2, 1)], Orientation::Right);
+        let events = world.advance(&[Orientation::Down, Orientation::Right]);
+        assert_eq!(snake_cut(&events), Some((1, 0, 1)));
+        assert_eq!(world.snake_cells(0), vec![Pos::new(3, 2), Pos::new(4, 2)]);
+        assert_eq!(world.snake_cells(1), vec![Pos::new(2, 1), Pos::new(2, 2)]);
+        assert_eq!(world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn heads_are_never_cut() {
+        let mut world = World::with_seed(8, 8, 1);
+        world.wall_collision = true;
+        world.snake_cutting = Some(Remains::Food(1));
+        add_long_snake(&mut world, &[(4, 0), (4, 1)], Orientation::Right);
+        add_long_snake(&mut world, &[(2, 2), (3, 2), (4, 2)], Orientation::Down);
+        let events = world.advance(&[Orientation::Right, Orientation::Down]);
+        assert_eq!(snake_cut(&events), None);
+        assert!(events.iter().any(|e| matches!(e, GameEvent::Collision(0, (4, 2)))));
+        // without cutting, biting a body is a collision as well
+        let mut world = about_to_bite(Remains::Food(1));
+        world.snake_cutting = None;
+        let events = world.advance(&[Orientation::Right, Orientation::Down]);
+        assert_eq!(snake_cut(&events), None);
+        assert!(events.iter().any(|e| matches!(e, GameEvent::Collision(0, (4, 2)))));
+    }
+}
+
@@ -0,0 +1,48 @@
+//! Keys for the incremental world hash. The hash of a world is the xor of the keys of all its
+//! cells, snakes and the snack count, so changing one part only needs two xors.
+//!
+//! Keys are derived from a fixed mixing function instead of a random table, they are the same
+//! for every run and every platform and there is no limit on the number of snakes or the growth
+//! value of food.
+
+use super::{Cell, Snake, Orientation};
+
+/// splitmix64 finalizer
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn orientation_code(orientation: Orientation) -> u64 {
+    match orientation {
+        Orientation::Up => 0,
+        Orientation::Down => 1,
+        Orientation::Left => 2,
+        Orientation::Right => 3
+    }
+}
+
+/// Key of `cell` at grid index `index`. Empty cells have key 0, an empty grid hashes to 0.
+pub fn cell_key(index: usize, cell: &Cell) -> u64 {
+    let code = match cell {
+        Cell::Empty => return 0,
+        Cell::Food(growth_value) => (*growth_value as u64) << 3 | 1,
+        Cell::Stone => 2,
+        Cell::Snake(id, orientation) => (*id as u64) << 5 | orientation_code(*orientation) << 3 | 3
+    };
+    mix(mix(index as u64) ^ code)
+}
+
+pub fn snake_key(player: usize, snake: &Snake) -> u64 {
+    let mut key = mix(0x736e_616b_6500_0000 ^ player as u64);
+    for field in &[snake.head.row, snake.head.col, snake.tail.row, snake.tail.col, snake.pending_growth, snake.length, snake.alive as usize, snake.ghost as usize] {
+        key = mix(key ^ *field as u64);
+    }
+    key
+}
+
+pub fn snacks_key(available_snacks: usize) -> u64 {
+    mix(0x736e_6163_6b73_0000 ^ available_snacks as u64)
+}
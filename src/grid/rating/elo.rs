@@ -0,0 +1,41 @@
+/// Rating of a new player.
+pub const INITIAL_ELO: f64 = 1500.0;
+/// How fast ratings change.
+pub const ELO_K: f64 = 32.0;
+
+/// Expected score of a player rated `rating` against one rated `opponent`, between 0 and 1.
+pub fn elo_expected(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+/// New rating after a game against each of `results`: the opponent's rating and the score
+/// against it (1 win, 0.5 draw, 0 loss).
+pub fn elo_update(rating: f64, results: &[(f64, f64)]) -> f64 {
+    rating + ELO_K * results.iter().map(|(opponent, score)| score - elo_expected(rating, *opponent)).sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_scores_add_up_to_one() {
+        assert_eq!(elo_expected(1500.0, 1500.0), 0.5);
+        // 400 points ahead means ten times the odds
+        assert!((elo_expected(1900.0, 1500.0) - 10.0 / 11.0).abs() < 1e-9);
+        assert!((elo_expected(1600.0, 1450.0) + elo_expected(1450.0, 1600.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn surprising_results_move_ratings_more() {
+        assert_eq!(elo_update(1500.0, &[(1500.0, 1.0)]), 1500.0 + ELO_K / 2.0);
+        assert_eq!(elo_update(1500.0, &[(1500.0, 0.5)]), 1500.0);
+        assert_eq!(elo_update(1500.0, &[]), 1500.0);
+        let upset = elo_update(1500.0, &[(1900.0, 1.0)]) - 1500.0;
+        let expected_win = elo_update(1900.0, &[(1500.0, 1.0)]) - 1900.0;
+        assert!((upset - ELO_K * 10.0 / 11.0).abs() < 1e-9);
+        assert!((expected_win - ELO_K / 11.0).abs() < 1e-9);
+        // the results of a rating period add up
+        assert_eq!(elo_update(1500.0, &[(1500.0, 1.0), (1500.0, 0.0)]), 1500.0);
+    }
+}
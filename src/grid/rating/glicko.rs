@@ -0,0 +1,84 @@
+use std::f64::consts::{LN_10, PI};
+
+/// Deviation never drops below this, so ratings keep adapting.
+const MIN_DEVIATION: f64 = 30.0;
+const Q: f64 = LN_10 / 400.0;
+
+/// Glicko rating: a rating together with its uncertainty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glicko {
+    pub rating: f64,
+    pub deviation: f64
+}
+
+impl Default for Glicko {
+    fn default() -> Glicko {
+        Glicko { rating: 1500.0, deviation: 350.0 }
+    }
+}
+
+/// Weight of a result against an opponent with deviation `deviation`.
+fn g(deviation: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * Q * Q * deviation * deviation / (PI * PI)).sqrt()
+}
+
+impl Glicko {
+    /// Expected score against `opponent`.
+    pub fn expected(&self, opponent: &Glicko) -> f64 {
+        1.0 / (1.0 + 10f64.powf(-g(opponent.deviation) * (self.rating - opponent.rating) / 400.0))
+    }
+    /// Rating after a rating period with the given results: the opponent and the score against
+    /// it (1 win, 0.5 draw, 0 loss).
+    pub fn update(&self, results: &[(Glicko, f64)]) -> Glicko {
+        if results.is_empty() {
+            return *self;
+        }
+        let mut information = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in results {
+            let weight = g(opponent.deviation);
+            let expected = self.expected(opponent);
+            information += weight * weight * expected * (1.0 - expected);
+            improvement += weight * (score - expected);
+        }
+        let d_squared = 1.0 / (Q * Q * information);
+        let precision = 1.0 / (self.deviation * self.deviation) + 1.0 / d_squared;
+        Glicko {
+            rating: self.rating + Q / precision * improvement,
+            deviation: (1.0 / precision).sqrt().max(MIN_DEVIATION)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worked_example() {
+        // the example from Glickman's description of the Glicko system: a player rated 1500
+        // with deviation 200 beats a 1400 and loses against a 1550 and a 1700
+        let player = Glicko { rating: 1500.0, deviation: 200.0 };
+        let results = [
+            (Glicko { rating: 1400.0, deviation: 30.0 }, 1.0),
+            (Glicko { rating: 1550.0, deviation: 100.0 }, 0.0),
+            (Glicko { rating: 1700.0, deviation: 300.0 }, 0.0)
+        ];
+        let updated = player.update(&results);
+        assert!((updated.rating - 1464.0).abs() < 0.5, "rating {}", updated.rating);
+        assert!((updated.deviation - 151.4).abs() < 0.5, "deviation {}", updated.deviation);
+    }
+
+    #[test]
+    fn deviation_shrinks_but_stays_above_the_minimum() {
+        let player = Glicko::default();
+        assert_eq!(player.update(&[]), player);
+        assert_eq!(player.expected(&player), 0.5);
+        let mut rating = player;
+        for _ in 0..1000 {
+            rating = rating.update(&[(Glicko::default(), 0.5)]);
+        }
+        assert_eq!(rating.deviation, MIN_DEVIATION);
+        assert!((rating.rating - 1500.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::model::{World, TurnResult};
+use super::{Glicko, INITIAL_ELO, elo_update};
+
+const HEADER: &str = "snakes-leaderboard 1";
+
+/// Ratings and record of an agent or a human player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    pub elo: f64,
+    pub glicko: Glicko,
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize
+}
+
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating { elo: INITIAL_ELO, glicko: Glicko::default(), games: 0, wins: 0, draws: 0, losses: 0 }
+    }
+}
+
+/// Ratings of everyone who played, by name.
+///
+/// Saved as text: a header line, then one line per player with Elo rating, Glicko rating and
+/// deviation, games, wins, draws, losses and the name.
+#[derive(Debug, Clone, Default)]
+pub struct Leaderboard {
+    ratings: BTreeMap<String, Rating>
+}
+
+impl Leaderboard {
+    pub fn new() -> Leaderboard {
+        Leaderboard::default()
+    }
+    /// Loads a leaderboard, an empty one if the file doesn't exist yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Leaderboard> {
+        match File::open(path) {
+            Ok(file) => Leaderboard::read(BufReader::new(file)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Leaderboard::new()),
+            Err(e) => Err(e)
+        }
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+    pub fn read<R: BufRead>(input: R) -> io::Result<Leaderboard> {
+        let mut lines = input.lines();
+        let header = lines.next().transpose()?;
+        if header.as_deref().map(str::trim) != Some(HEADER) {
+            return Err(invalid(1, "not a leaderboard"));
+        }
+        let mut leaderboard = Leaderboard::new();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(8, ' ').collect();
+            if fields.len() != 8 {
+                return Err(invalid(i+2, "expected 8 fields"));
+            }
+            let number = |field: &str| field.parse::<f64>().map_err(|_| invalid(i+2, &format!("invalid number '{}'", field)));
+            let count = |field: &str| field.parse::<usize>().map_err(|_| invalid(i+2, &format!("invalid count '{}'", field)));
+            let rating = Rating {
+                elo: number(fields[0])?,
+                glicko: Glicko { rating: number(fields[1])?, deviation: number(fields[2])? },
+                games: count(fields[3])?,
+                wins: count(fields[4])?,
+                draws: count(fields[5])?,
+                losses: count(fields[6])?
+            };
+            leaderboard.ratings.insert(fields[7].to_string(), rating);
+        }
+        Ok(leaderboard)
+    }
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        for (name, r) in &self.ratings {
+            writeln!(out, "{:.2} {:.2} {:.2} {} {} {} {} {}", r.elo, r.glicko.rating, r.glicko.deviation, r.games, r.wins, r.draws, r.losses, name)?;
+        }
+        Ok(())
+    }
+    /// Rating of `name`, the initial one if they never played.
+    pub fn rating(&self, name: &str) -> Rating {
+        self.ratings.get(name).cloned().unwrap_or_default()
+    }
+    /// Names sorted by Elo rating, best first.
+    pub fn ranking(&self) -> Vec<(&str, &Rating)> {
+        let mut ranking: Vec<(&str, &Rating)> = self.ratings.iter().map(|(name, r)| (name.as_str(), r)).collect();
+        ranking.sort_by(|a, b| b.1.elo.partial_cmp(&a.1.elo).unwrap());
+        ranking
+    }
+    /// Updates the ratings with the outcome of a finished game, `names[i]` played snake `i`.
+    /// Winners beat all losers. In a draw the snakes that died together draw against each other
+    /// and beat the losers that were out before. A name that occurs more than once plays one
+    /// game and doesn't play against itself, its best snake counts for its record.
+    pub fn record_game<S: AsRef<str>>(&mut self, names: &[S], world: &World) {
+        debug_assert_eq!(names.len(), world.snakes.len());
+        let names: Vec<&str> = names.iter().map(|name| name.as_ref()).collect();
+        let mut scores = vec![Vec::new(); names.len()];
+        let mut outcomes = vec![None; names.len()];
+        match world.turn_result {
+            TurnResult::Ok => return,
+            TurnResult::Draw => {
+                let drawn: Vec<usize> = (0..names.len()).filter(|s| !world.losers.contains(s)).collect();
+                for a in &drawn {
+                    outcomes[*a] = Some(0.5);
+                    scores[*a] = drawn.iter().filter(|b| *b != a).map(|b| (*b, 0.5)).collect();
+                    scores[*a].extend(world.losers.iter().map(|l| (*l, 1.0)));
+                }
+                for l in &world.losers {
+                    outcomes[*l] = Some(0.0);
+                    scores[*l].extend(drawn.iter().map(|a| (*a, 0.0)));
+                }
+            },
+            TurnResult::GameOver => {
+                for w in &world.winners {
+                    outcomes[*w] = Some(1.0);
+                    scores[*w].extend(world.losers.iter().map(|l| (*l, 1.0)));
+                }
+                for l in &world.losers {
+                    outcomes[*l] = Some(0.0);
+                    scores[*l].extend(world.winners.iter().map(|w| (*w, 0.0)));
+                }
+            }
+        }
+        // all updates are based on the ratings before the game
+        let before: Vec<Rating> = names.iter().map(|name| self.rating(name)).collect();
+        let mut results: BTreeMap<&str, Vec<(usize, f64)>> = BTreeMap::new();
+        let mut best: BTreeMap<&str, Option<f64>> = BTreeMap::new();
+        for (a, name) in names.iter().enumerate() {
+            let entry = results.entry(name).or_default();
+            entry.extend(scores[a].iter().filter(|(b, _)| names[*b] != *name));
+            let outcome = best.entry(name).or_default();
+            if outcomes[a] > *outcome {
+                *outcome = outcomes[a];
+            }
+        }
+        for (name, results) in results {
+            let old = self.rating(name);
+            let elo_results: Vec<(f64, f64)> = results.iter().map(|(b, score)| (before[*b].elo, *score)).collect();
+            let glicko_results: Vec<(Glicko, f64)> = results.iter().map(|(b, score)| (before[*b].glicko, *score)).collect();
+            let rating = self.ratings.entry(name.to_string()).or_default();
+            rating.games += 1;
+            // a game against itself only is neither won nor lost
+            match best[name] {
+                _ if results.is_empty() => (),
+                Some(score) if score > 0.5 => rating.wins += 1,
+                Some(score) if score < 0.5 => rating.losses += 1,
+                Some(_) => rating.draws += 1,
+                None => ()
+            }
+            rating.elo = elo_update(old.elo, &elo_results);
+            rating.glicko = old.glicko.update(&glicko_results);
+        }
+    }
+}
+
+impl fmt::Display for Leaderboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ranking = self.ranking();
+        let width = ranking.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(4);
+        writeln!(f, "{:width$}  {:>7}  {:>7}  {:>5}  {:>5}  {:>4}  {:>4}  {:>4}", "Name", "Elo", "Glicko", "RD", "Games", "W", "D", "L", width = width)?;
+        for (name, r) in ranking {
+            writeln!(f, "{:width$}  {:>7.1}  {:>7.1}  {:>5.1}  {:>5}  {:>4}  {:>4}  {:>4}",
+                name, r.elo, r.glicko.rating, r.glicko.deviation, r.games, r.wins, r.draws, r.losses, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(line: usize, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Orientation;
+
+    /// Finished game of `snakes` snakes.
+    fn finished(snakes: usize, turn_result: TurnResult, winners: &[usize], losers: &[usize]) -> World {
+        let mut world = World::with_seed(6, 6, 1);
+        for s in 0..snakes {
+            world.add_snake((1, s+1), Orientation::Down).unwrap();
+        }
+        world.turn_result = turn_result;
+        world.winners = winners.to_vec();
+        world.losers = losers.to_vec();
+        world
+    }
+
+    /// Leaderboard after alice beat bob once.
+    fn alice_beat_bob() -> Leaderboard {
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.record_game(&["alice", "bob"], &finished(2, TurnResult::GameOver, &[0], &[1]));
+        leaderboard
+    }
+
+    fn record(rating: &Rating) -> (usize, usize, usize, usize) {
+        (rating.games, rating.wins, rating.draws, rating.losses)
+    }
+
+    fn parse_error(text: &str) -> String {
+        let e = Leaderboard::read(text.as_bytes()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        e.to_string()
+    }
+
+    #[test]
+    fn games_update_the_record() {
+        let leaderboard = alice_beat_bob();
+        let (alice, bob) = (leaderboard.rating("alice"), leaderboard.rating("bob"));
+        assert_eq!((alice.games, alice.wins, alice.losses), (1, 1, 0));
+        assert_eq!((bob.games, bob.wins, bob.losses), (1, 0, 1));
+        assert_eq!(alice.elo, INITIAL_ELO + 16.0);
+        assert!(alice.glicko.rating > bob.glicko.rating);
+        assert_eq!(leaderboard.ranking()[0].0, "alice");
+        assert_eq!(leaderboard.rating("carol"), Rating::default());
+    }
+
+    #[test]
+    fn snakes_out_before_a_draw_lose() {
+        // carol died before alice and bob died together
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.record_game(&["alice", "bob", "carol"], &finished(3, TurnResult::Draw, &[], &[2]));
+        assert_eq!(record(&leaderboard.rating("alice")), (1, 0, 1, 0));
+        assert_eq!(record(&leaderboard.rating("bob")), (1, 0, 1, 0));
+        assert_eq!(record(&leaderboard.rating("carol")), (1, 0, 0, 1));
+        assert!(leaderboard.rating("alice").elo > INITIAL_ELO);
+        assert_eq!(leaderboard.rating("alice").elo, leaderboard.rating("bob").elo);
+    }
+
+    #[test]
+    fn duplicate_names_play_one_game() {
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.record_game(&["bot", "bob", "bot"], &finished(3, TurnResult::GameOver, &[0], &[1, 2]));
+        assert_eq!(record(&leaderboard.rating("bot")), (1, 1, 0, 0));
+        assert_eq!(record(&leaderboard.rating("bob")), (1, 0, 0, 1));
+        // a game against itself only counts as a game
+        leaderboard.record_game(&["bot", "bot"], &finished(2, TurnResult::GameOver, &[0], &[1]));
+        assert_eq!(record(&leaderboard.rating("bot")), (2, 1, 0, 0));
+    }
+
+    #[test]
+    fn round_trip() {
+        let leaderboard = alice_beat_bob();
+        let mut out = Vec::new();
+        leaderboard.write(&mut out).unwrap();
+        let read = Leaderboard::read(&out[..]).unwrap();
+        for name in &["alice", "bob"] {
+            let (written, read) = (leaderboard.rating(name), read.rating(name));
+            // ratings are saved with two decimals
+            assert!((written.elo - read.elo).abs() < 0.01);
+            assert!((written.glicko.rating - read.glicko.rating).abs() < 0.01);
+            assert!((written.glicko.deviation - read.glicko.deviation).abs() < 0.01);
+            assert_eq!((written.games, written.wins, written.draws, written.losses), (read.games, read.wins, read.draws, read.losses));
+        }
+        // names may contain spaces
+        let text = format!("{}\n1500 1500 350 0 0 0 0 space explorer\n", HEADER);
+        assert!(Leaderboard::read(text.as_bytes()).unwrap().ratings.contains_key("space explorer"));
+    }
+
+    #[test]
+    fn broken_files_are_rejected() {
+        assert_eq!(parse_error("snakes-replay 1\n"), "line 1: not a leaderboard");
+        assert_eq!(parse_error(""), "line 1: not a leaderboard");
+        assert_eq!(parse_error(&format!("{}\n\n1500 1500 350 0 0 0\n", HEADER)), "line 3: expected 8 fields");
+        assert_eq!(parse_error(&format!("{}\n1500 abc 350 0 0 0 0 bob\n", HEADER)), "line 2: invalid number 'abc'");
+        assert_eq!(parse_error(&format!("{}\n1500 1500 350 -1 0 0 0 bob\n", HEADER)), "line 2: invalid count '-1'");
+    }
+}
@@ -0,0 +1,7 @@
+mod elo;
+mod glicko;
+mod leaderboard;
+
+pub use elo::*;
+pub use glicko::*;
+pub use leaderboard::*;
@@ -0,0 +1,3 @@
+mod recording;
+
+pub use recording::*;
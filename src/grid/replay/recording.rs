@@ -0,0 +1,457 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::model::{Game, GameMode, World, Grid, Cell, Snake, Pos, PlayerInput, Orientation, Remains, Violation, random_seed};
+
+const HEADER: &str = "snakes-replay 1";
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(usize, String), // line number, description
+    /// the recorded world breaks the invariants of `World::validate`
+    InvalidWorld(Vec<Violation>)
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Format(line, msg) => write!(f, "line {}: {}", line, msg),
+            ReplayError::InvalidWorld(violations) => {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                write!(f, "invalid world: {}", violations.join(", "))
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> ReplayError {
+        ReplayError::Io(e)
+    }
+}
+
+/// Everything needed to play a game again: the world at the start of the recording, the seed
+/// of the world's random number generator and the inputs of every turn. The world hash after each
+/// turn is stored as well, to detect replays that don't reproduce the recorded game.
+#[derive(Clone)]
+pub struct Replay {
+    initial: Game,
+    turns: Vec<Vec<PlayerInput>>,
+    hashes: Vec<Option<u64>>
+}
+
+impl Replay {
+    /// Starts recording `game`. The world is reseeded, so the following turns only depend on the
+    /// recorded seed and inputs.
+    pub fn start(game: &mut Game) -> Replay {
+        game.world.reseed(random_seed());
+        Replay {
+            initial: game.clone(),
+            turns: Vec::new(),
+            hashes: Vec::new()
+        }
+    }
+    /// Appends a turn, `world_hash` is the hash of the world after the turn was played.
+    pub fn push_turn(&mut self, inputs: &[PlayerInput], world_hash: u64) {
+        self.turns.push(inputs.to_vec());
+        self.hashes.push(Some(world_hash));
+    }
+    pub fn turns(&self) -> &[Vec<PlayerInput>] {
+        &self.turns
+    }
+    /// Recorded world hash after `turn`, if known.
+    pub fn hash_after(&self, turn: usize) -> Option<u64> {
+        self.hashes.get(turn).cloned().unwrap_or(None)
+    }
+    /// Plays all turns and compares the world hashes with the recorded ones. Returns the first
+    /// turn that differs.
+    pub fn verify(&self) -> Result<(), usize> {
+        let mut game = self.game();
+        for (turn, inputs) in self.turns.iter().enumerate() {
+            game.advance(inputs);
+            if self.hash_after(turn).is_some_and(|hash| hash != game.world.hash()) {
+                return Err(turn);
+            }
+        }
+        Ok(())
+    }
+    pub fn seed(&self) -> u64 {
+        self.initial.world.seed()
+    }
+    /// The game as it was when the recording started. Advancing it with `turns()` reproduces
+    /// the recorded game.
+    pub fn game(&self) -> Game {
+        self.initial.clone()
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        Replay::read(BufReader::new(File::open(path)?))
+    }
+    pub fn write<W: Write>(&self, out: &mut W) -> Result<(), ReplayError> {
+        let world = &self.initial.world;
+        writeln!(out, "{}", HEADER)?;
+        writeln!(out, "size {} {}", world.grid.rows(), world.grid.cols())?;
+        writeln!(out, "seed {}", world.seed())?;
+        writeln!(out, "iteration {}", self.initial.iteration())?;
+        writeln!(out, "rules mode={} wall_collision={} lose_on_collision={} max_snacks={} dead_snake_food={} snake_cutting={} fog_of_war={}",
+            mode_token(world.mode), world.wall_collision as u8, self.initial.lose_on_collision as u8,
+            self.initial.max_snacks, optional_token(world.dead_snake_food), remains_token(world.snake_cutting),
+            optional_token(world.fog_of_war))?;
+        for snake in &world.snakes {
+            writeln!(out, "snake {} {} {} {} {} {} {}", snake.head.row, snake.head.col, snake.tail.row, snake.tail.col,
+                snake.pending_growth, snake.length, snake.alive as u8)?;
+        }
+        writeln!(out, "grid")?;
+        for row in 0..world.grid.rows() {
+            let cells: Vec<String> = (0..world.grid.cols()).map(|col| cell_token(world.grid.get((row, col)))).collect();
+            writeln!(out, "{}", cells.join(" "))?;
+        }
+        writeln!(out, "turns")?;
+        for (turn, hash) in self.turns.iter().zip(&self.hashes) {
+            let mut tokens: Vec<String> = turn.iter().map(|i| input_token(*i).to_string()).collect();
+            if let Some(hash) = hash {
+                tokens.push(format!("@{:016x}", hash));
+            }
+            writeln!(out, "{}", tokens.join(" "))?;
+        }
+        Ok(())
+    }
+    pub fn read<R: BufRead>(input: R) -> Result<Replay, ReplayError> {
+        let mut lines = Lines { lines: input.lines(), number: 0 };
+        if lines.next_line()? != HEADER {
+            return Err(lines.error("not a replay file"));
+        }
+        let size = lines.keyword("size")?;
+        let (rows, cols) = match size.as_slice() {
+            [rows, cols] => (lines.number(rows)?, lines.number(cols)?),
+            _ => return Err(lines.error("expected: size <rows> <cols>"))
+        };
+        if rows < 2 || cols < 1 {
+            return Err(lines.error("grid too small"));
+        }
+        let seed = match lines.keyword("seed")?.as_slice() {
+            [seed] => lines.number(seed)?,
+            _ => return Err(lines.error("expected: seed <seed>"))
+        };
+        let iteration = match lines.keyword("iteration")?.as_slice() {
+            [iteration] => lines.number(iteration)?,
+            _ => return Err(lines.error("expected: iteration <iteration>"))
+        };
+        let rules = lines.keyword("rules")?;
+        let mut mode = GameMode::Classic;
+        let mut wall_collision = false;
+        let mut lose_on_collision = true;
+        let mut max_snacks = 0;
+        let mut dead_snake_food = None;
+        let mut snake_cutting = None;
+        let mut fog_of_war = None;
+        for rule in rules {
+            let mut kv = rule.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let value = kv.next().ok_or_else(|| lines.error("expected: <rule>=<value>"))?;
+            match key {
+                "mode" => mode = parse_mode(value).ok_or_else(|| lines.error(&format!("invalid mode: {}", value)))?,
+                "wall_collision" => wall_collision = lines.number::<u8>(value)? != 0,
+                "lose_on_collision" => lose_on_collision = lines.number::<u8>(value)? != 0,
+                "max_snacks" => max_snacks = lines.number(value)?,
+                "dead_snake_food" => dead_snake_food = lines.optional_number(value)?,
+                "snake_cutting" => snake_cutting = parse_remains(value).ok_or_else(|| lines.error(&format!("invalid remains: {}", value)))?,
+                "fog_of_war" => fog_of_war = lines.optional_number(value)?,
+                _ => return Err(lines.error(&format!("unknown rule: {}", key)))
+            }
+        }
+        let mut snakes = Vec::new();
+        loop {
+            let line = lines.next_line()?;
+            if line == "grid" {
+                break;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["snake", hr, hc, tr, tc, growth, length, alive] => {
+                    let mut snake = Snake::new(Pos::new(lines.number(hr)?, lines.number(hc)?), Pos::new(lines.number(tr)?, lines.number(tc)?));
+                    snake.pending_growth = lines.number(growth)?;
+                    snake.length = lines.number(length)?;
+                    snake.alive = lines.number::<u8>(alive)? != 0;
+                    if snake.head.row >= rows || snake.head.col >= cols || snake.tail.row >= rows || snake.tail.col >= cols {
+                        return Err(lines.error("snake outside of grid"));
+                    }
+                    snakes.push(snake);
+                },
+                _ => return Err(lines.error("expected: snake <head row> <head col> <tail row> <tail col> <pending growth> <length> <alive>"))
+            }
+        }
+        let mut grid = Grid::from_elem(Cell::Empty, rows, cols);
+        for row in 0..rows {
+            let line = lines.next_line()?;
+            let cells: Vec<&str> = line.split_whitespace().collect();
+            if cells.len() != cols {
+                return Err(lines.error(&format!("expected {} cells", cols)));
+            }
+            for (col, token) in cells.iter().enumerate() {
+                let cell = parse_cell(token).ok_or_else(|| lines.error(&format!("invalid cell: {}", token)))?;
+                if let Cell::Snake(id, _) = cell {
+                    if id >= snakes.len() {
+                        return Err(lines.error(&format!("unknown snake: {}", id)));
+                    }
+                }
+                grid.set((row, col), cell);
+            }
+        }
+        if lines.next_line()? != "turns" {
+            return Err(lines.error("expected: turns"));
+        }
+        let mut turns = Vec::new();
+        let mut hashes = Vec::new();
+        while let Some(line) = lines.next_optional_line()? {
+            let mut turn = Vec::new();
+            let mut hash = None;
+            for token in line.split_whitespace() {
+                if let Some(hex) = token.strip_prefix('@') {
+                    hash = Some(u64::from_str_radix(hex, 16).map_err(|_| lines.error(&format!("invalid hash: {}", token)))?);
+                } else {
+                    turn.push(parse_input(token).ok_or_else(|| lines.error(&format!("invalid input: {}", token)))?);
+                }
+            }
+            if turn.len() != snakes.len() {
+                return Err(lines.error(&format!("expected {} inputs", snakes.len())));
+            }
+            turns.push(turn);
+            hashes.push(hash);
+        }
+        let mut world = World::from_parts(grid, snakes, seed);
+        world.wall_collision = wall_collision;
+        world.snake_cutting = snake_cutting;
+        world.mode = mode;
+        world.fog_of_war = fog_of_war;
+        world.dead_snake_food = dead_snake_food;
+        let mut initial = Game::resume(world, iteration);
+        initial.lose_on_collision = lose_on_collision;
+        initial.max_snacks = max_snacks;
+        // a broken world would only fail later, while advancing
+        initial.world.validate().map_err(ReplayError::InvalidWorld)?;
+        Ok(Replay { initial, turns, hashes })
+    }
+}
+
+struct Lines<R: BufRead> {
+    lines: io::Lines<R>,
+    number: usize
+}
+
+impl<R: BufRead> Lines<R> {
+    fn error(&self, msg: &str) -> ReplayError {
+        ReplayError::Format(self.number, msg.to_string())
+    }
+    fn next_optional_line(&mut self) -> Result<Option<String>, ReplayError> {
+        loop {
+            match self.lines.next() {
+                None => return Ok(None),
+                Some(line) => {
+                    self.number += 1;
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        return Ok(Some(line.trim().to_string()));
+                    }
+                }
+            }
+        }
+    }
+    fn next_line(&mut self) -> Result<String, ReplayError> {
+        match self.next_optional_line()? {
+            Some(line) => Ok(line),
+            None => Err(self.error("unexpected end of file"))
+        }
+    }
+    /// Reads a line of the form `<keyword> <arguments>` and returns the arguments.
+    fn keyword(&mut self, keyword: &str) -> Result<Vec<String>, ReplayError> {
+        let line = self.next_line()?;
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some(keyword) {
+            return Err(self.error(&format!("expected: {}", keyword)));
+        }
+        Ok(fields.map(|f| f.to_string()).collect())
+    }
+    fn number<T: std::str::FromStr>(&self, token: &str) -> Result<T, ReplayError> {
+        token.parse().map_err(|_| self.error(&format!("invalid number: {}", token)))
+    }
+    /// A number or `none`, see `optional_token`.
+    fn optional_number<T: std::str::FromStr>(&self, token: &str) -> Result<Option<T>, ReplayError> {
+        match token {
+            "none" => Ok(None),
+            _ => self.number(token).map(Some)
+        }
+    }
+}
+
+/// `none` for unset rules, 0 is a value of its own.
+fn optional_token(value: Option<usize>) -> String {
+    match value {
+        None => "none".to_string(),
+        Some(value) => value.to_string()
+    }
+}
+
+fn orientation_token(orientation: Orientation) -> &'static str {
+    match orientation {
+        Orientation::Up => "U",
+        Orientation::Down => "D",
+        Orientation::Left => "L",
+        Orientation::Right => "R"
+    }
+}
+
+fn parse_orientation(token: &str) -> Option<Orientation> {
+    match token {
+        "U" => Some(Orientation::Up),
+        "D" => Some(Orientation::Down),
+        "L" => Some(Orientation::Left),
+        "R" => Some(Orientation::Right),
+        _ => None
+    }
+}
+
+fn input_token(input: PlayerInput) -> &'static str {
+    match input {
+        PlayerInput::DoNothing => ".",
+        PlayerInput::Go(orientation) => orientation_token(orientation),
+        PlayerInput::TurnLeft => "<",
+        PlayerInput::TurnRight => ">"
+    }
+}
+
+fn parse_input(token: &str) -> Option<PlayerInput> {
+    match token {
+        "." => Some(PlayerInput::DoNothing),
+        "<" => Some(PlayerInput::TurnLeft),
+        ">" => Some(PlayerInput::TurnRight),
+        _ => parse_orientation(token).map(PlayerInput::Go)
+    }
+}
+
+fn mode_token(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Classic => "classic",
+        GameMode::Tron => "tron"
+    }
+}
+
+fn parse_mode(token: &str) -> Option<GameMode> {
+    match token {
+        "classic" => Some(GameMode::Classic),
+        "tron" => Some(GameMode::Tron),
+        _ => None
+    }
+}
+
+fn remains_token(remains: Option<Remains>) -> String {
+    match remains {
+        None => "none".to_string(),
+        Some(Remains::Stones) => "stones".to_string(),
+        Some(Remains::Food(growth_value)) => format!("food{}", growth_value)
+    }
+}
+
+fn parse_remains(token: &str) -> Option<Option<Remains>> {
+    match token {
+        "none" => Some(None),
+        "stones" => Some(Some(Remains::Stones)),
+        _ if token.starts_with("food") => token[4..].parse().ok().map(|growth_value| Some(Remains::Food(growth_value))),
+        _ => None
+    }
+}
+
+fn cell_token(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => "_".to_string(),
+        Cell::Food(growth_value) => format!("F{}", growth_value),
+        Cell::Stone => "#".to_string(),
+        Cell::Snake(id, orientation) => format!("{}{}", id, orientation_token(*orientation))
+    }
+}
+
+fn parse_cell(token: &str) -> Option<Cell> {
+    match token {
+        "_" => Some(Cell::Empty),
+        "#" => Some(Cell::Stone),
+        _ if token.starts_with('F') => token[1..].parse().ok().map(Cell::Food),
+        _ => {
+            // the orientation is the last character, which might not be a single byte
+            let (last, _) = token.char_indices().last()?;
+            let (id, orientation) = token.split_at(last);
+            Some(Cell::Snake(id.parse().ok()?, parse_orientation(orientation)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded() -> Replay {
+        let mut game = Game::new(World::with_seed(6, 6, 1));
+        game.world.add_snake((2, 2), Orientation::Down).unwrap();
+        game.world.fog_of_war = Some(3);
+        game.world.dead_snake_food = Some(0);
+        let mut replay = Replay::start(&mut game);
+        for _ in 0..3 {
+            let inputs = [PlayerInput::Go(Orientation::Right)];
+            game.advance(&inputs);
+            replay.push_turn(&inputs, game.world.hash());
+        }
+        replay
+    }
+
+    fn text(replay: &Replay) -> String {
+        let mut out = Vec::new();
+        replay.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = recorded();
+        let read = Replay::read(text(&replay).as_bytes()).unwrap();
+        assert_eq!(read.seed(), replay.seed());
+        assert_eq!(read.turns(), replay.turns());
+        assert_eq!(read.game().world.fog_of_war, Some(3));
+        assert_eq!(read.game().world.dead_snake_food, Some(0));
+        let unset = text(&replay).replace("fog_of_war=3", "fog_of_war=none");
+        assert_eq!(Replay::read(unset.as_bytes()).unwrap().game().world.fog_of_war, None);
+        assert_eq!(read.verify(), Ok(()));
+    }
+
+    #[test]
+    fn broken_world_is_rejected() {
+        // the tail of the snake goes missing
+        let broken = text(&recorded()).replacen("0D", "_", 1);
+        match Replay::read(broken.as_bytes()) {
+            Err(ReplayError::InvalidWorld(violations)) => assert!(!violations.is_empty()),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("broken world was accepted")
+        }
+    }
+
+    #[test]
+    fn odd_cell_tokens_are_format_errors() {
+        assert!(parse_cell("0D").is_some());
+        for token in &["D", "0", "0é", "é", "x0D", "F"] {
+            assert!(parse_cell(token).is_none(), "{}", token);
+        }
+        let broken = text(&recorded()).replacen("0D", "0é", 1);
+        match Replay::read(broken.as_bytes()) {
+            Err(ReplayError::Format(..)) => (),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("broken cell was accepted")
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use crate::model::{Game, GameMode, World, Orientation, PlayerInput, Remains, TurnResult, GameEvent, Player};
+use super::Features;
+
+/// The actions an agent can choose from, numbered from 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionSpace {
+    /// 0 to 3: up, down, left, right
+    Absolute,
+    /// 0 to 2: straight on, turn left, turn right
+    Relative
+}
+
+impl ActionSpace {
+    /// Number of actions.
+    pub fn size(&self) -> usize {
+        match self {
+            ActionSpace::Absolute => 4,
+            ActionSpace::Relative => 3
+        }
+    }
+    pub fn input(&self, action: usize) -> PlayerInput {
+        debug_assert!(action < self.size());
+        match self {
+            ActionSpace::Absolute => PlayerInput::Go(Orientation::all()[action]),
+            ActionSpace::Relative => match action {
+                1 => PlayerInput::TurnLeft,
+                2 => PlayerInput::TurnRight,
+                _ => PlayerInput::DoNothing
+            }
+        }
+    }
+}
+
+/// Rewards a player gets in a step.
+#[derive(Debug, Clone, Copy)]
+pub struct RewardShaping {
+    /// per growth value of eaten food
+    pub food: f32,
+    /// when colliding with a wall, a stone or a snake
+    pub collision: f32,
+    /// when winning the game
+    pub win: f32,
+    /// every step alive, e.g. a small negative value to hurry up
+    pub step: f32
+}
+
+impl Default for RewardShaping {
+    fn default() -> RewardShaping {
+        RewardShaping { food: 1.0, collision: -1.0, win: 1.0, step: 0.0 }
+    }
+}
+
+/// Rules and encoding of an `Env`.
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+    pub rows: usize,
+    pub cols: usize,
+    pub snakes: usize,
+    pub stones: usize,
+    pub wall_collision: bool,
+    pub mode: GameMode,
+    pub max_snacks: usize,
+    pub dead_snake_food: Option<usize>,
+    pub snake_cutting: Option<Remains>,
+    /// episodes are cut off after this many steps
+    pub max_steps: usize,
+    pub features: Features,
+    pub action_space: ActionSpace,
+    pub rewards: RewardShaping
+}
+
+impl Default for EnvConfig {
+    fn default() -> EnvConfig {
+        EnvConfig {
+            rows: 10,
+            cols: 10,
+            snakes: 1,
+            stones: 3,
+            wall_collision: true,
+            mode: GameMode::Classic,
+            max_snacks: 2,
+            dead_snake_food: None,
+            snake_cutting: None,
+            max_steps: 1000,
+            features: Features::HandCrafted,
+            action_space: ActionSpace::Relative,
+            rewards: RewardShaping::default()
+        }
+    }
+}
+
+impl EnvConfig {
+    /// Number of values in the observation of one player.
+    pub fn observation_len(&self) -> usize {
+        self.features.len(self.rows, self.cols)
+    }
+    /// Checks that episodes can be started with these rules, e.g. that the grid has space for
+    /// all snakes.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rows < 2 || self.cols < 1 {
+            return Err(format!("a {}x{} grid is too small", self.rows, self.cols));
+        }
+        if self.snakes == 0 {
+            return Err("an environment needs at least one snake".to_string());
+        }
+        self.start_world(0).map(|_| ())
+    }
+    /// The world an episode starts in, stones are placed depending on `seed`.
+    fn start_world(&self, seed: u64) -> Result<World, String> {
+        let mut world = World::with_seed(self.rows, self.cols, seed);
+        world.wall_collision = self.wall_collision;
+        world.snake_cutting = self.snake_cutting;
+        world.dead_snake_food = self.dead_snake_food;
+        world.mode = self.mode;
+        for i in 0..self.snakes {
+            if world.add_snake((1, (i*4+2) % self.cols), Orientation::Down).is_err() {
+                return Err(format!("there is no space for {} snakes on a {}x{} grid", self.snakes, self.rows, self.cols));
+            }
+        }
+        world.place_stones_randomly(self.stones);
+        Ok(world)
+    }
+}
+
+/// What happened in a step besides the rewards.
+#[derive(Debug, Clone)]
+pub struct StepInfo {
+    pub turn_result: TurnResult,
+    pub iteration: usize,
+    /// the episode was cut off by `max_steps`
+    pub truncated: bool,
+    pub lengths: Vec<usize>,
+    pub events: Vec<GameEvent>
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub observations: Vec<Vec<f32>>,
+    pub rewards: Vec<f32>,
+    pub dones: Vec<bool>,
+    pub info: StepInfo
+}
+
+/// Reinforcement learning environment: a game played in episodes, with one observation, action
+/// and reward per snake.
+pub struct Env {
+    config: EnvConfig,
+    pub game: Game,
+    steps: usize,
+    dones: Vec<bool>
+}
+
+impl Env {
+    /// Fails if `config` doesn't pass `EnvConfig::validate`.
+    pub fn new(config: EnvConfig) -> Result<Env, String> {
+        config.validate()?;
+        let mut env = Env {
+            game: Game::new(World::with_seed(config.rows, config.cols, 0)),
+            config,
+            steps: 0,
+            dones: Vec::new()
+        };
+        env.reset(0);
+        Ok(env)
+    }
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+    pub fn players(&self) -> usize {
+        self.config.snakes
+    }
+    /// Starts a new episode, the game only depends on `seed`. Returns the observations.
+    pub fn reset(&mut self, seed: u64) -> Vec<Vec<f32>> {
+        let config = &self.config;
+        // snakes are placed independent of the seed, `new` validated that they fit
+        let world = config.start_world(seed).expect("config was validated");
+        let mut game = Game::new(world);
+        game.max_snacks = config.max_snacks;
+        self.game = game;
+        self.steps = 0;
+        self.dones = vec![false; config.snakes];
+        self.observations()
+    }
+    /// Whether the episode is over for all players.
+    pub fn is_done(&self) -> bool {
+        self.dones.iter().all(|done| *done)
+    }
+    pub fn observe(&self, player: Player, out: &mut [f32]) {
+        self.config.features.encode_into(&self.game.world, player, out);
+    }
+    pub fn observations(&self) -> Vec<Vec<f32>> {
+        (0..self.players()).map(|s| self.config.features.encode(&self.game.world, s)).collect()
+    }
+    /// Plays one turn with an action of `config.action_space` for every player. Actions of
+    /// players that are done are ignored. Panics if the episode is over, call `reset` first.
+    pub fn step(&mut self, actions: &[usize]) -> Step {
+        let (rewards, info) = self.advance(actions);
+        Step {
+            observations: self.observations(),
+            rewards,
+            dones: self.dones.clone(),
+            info
+        }
+    }
+    /// Players for which the episode is over.
+    pub fn dones(&self) -> &[bool] {
+        &self.dones
+    }
+    /// `step` without encoding the observations, returns rewards and info.
+    pub(crate) fn advance(&mut self, actions: &[usize]) -> (Vec<f32>, StepInfo) {
+        assert!(!self.is_done(), "the episode is over, reset the environment first");
+        let inputs: Vec<PlayerInput> = actions.iter().zip(&self.dones)
+            .map(|(action, done)| if *done { PlayerInput::DoNothing } else { self.config.action_space.input(*action) })
+            .collect();
+        self.game.advance(&inputs);
+        self.steps += 1;
+        let rewards = self.rewards();
+        let world = &self.game.world;
+        let truncated = self.steps >= self.config.max_steps;
+        for (s, done) in self.dones.iter_mut().enumerate() {
+            *done = *done || !world.snakes[s].alive || world.turn_result != TurnResult::Ok || truncated;
+        }
+        let info = StepInfo {
+            turn_result: world.turn_result,
+            iteration: self.game.iteration(),
+            truncated,
+            lengths: world.snakes.iter().map(|snake| snake.length).collect(),
+            events: self.game.events().to_vec()
+        };
+        (rewards, info)
+    }
+    /// Rewards of the last turn.
+    fn rewards(&self) -> Vec<f32> {
+        let shaping = self.config.rewards;
+        let mut rewards: Vec<f32> = self.dones.iter().map(|done| if *done { 0.0 } else { shaping.step }).collect();
+        let mut collided = vec![false; self.players()];
+        for event in self.game.events() {
+            match event {
+                GameEvent::FoodConsumed(s, growth_value) => rewards[*s] += shaping.food * *growth_value as f32,
+                GameEvent::Collision(s, _) => collided[*s] = true,
+                _ => ()
+            }
+        }
+        for (s, collided) in collided.iter().enumerate() {
+            if *collided {
+                rewards[s] += shaping.collision;
+            }
+        }
+        if self.game.world.turn_result == TurnResult::GameOver {
+            for s in &self.game.world.winners {
+                rewards[*s] += shaping.win;
+            }
+        }
+        rewards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Pos;
+    use crate::rl::VecEnv;
+
+    #[test]
+    fn crowded_configs_are_rejected() {
+        assert!(Env::new(EnvConfig::default()).is_ok());
+        // the start columns of three snakes wrap around a grid four cells wide
+        let crowded = EnvConfig { cols: 4, snakes: 3, ..EnvConfig::default() };
+        assert!(crowded.validate().is_err());
+        assert!(Env::new(crowded.clone()).is_err());
+        assert!(VecEnv::new(crowded, 2, 1).is_err());
+        assert!(Env::new(EnvConfig { rows: 1, ..EnvConfig::default() }).is_err());
+        assert!(Env::new(EnvConfig { snakes: 0, ..EnvConfig::default() }).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "the episode is over")]
+    fn stepping_a_finished_episode_panics() {
+        let mut env = Env::new(EnvConfig { max_steps: 1, ..EnvConfig::default() }).unwrap();
+        assert!(env.step(&[0]).dones[0]);
+        env.step(&[0]);
+    }
+
+    /// Single snake on an empty 10x10 grid with walls, it starts at (1, 2) heading down.
+    fn empty_grid() -> EnvConfig {
+        EnvConfig { stones: 0, max_snacks: 0, ..EnvConfig::default() }
+    }
+
+    #[test]
+    fn episode_ends_at_the_wall() {
+        let mut env = Env::new(empty_grid()).unwrap();
+        // eight steps straight down to the last row, the ninth one hits the wall
+        for _ in 0..8 {
+            let step = env.step(&[0]);
+            assert_eq!(step.rewards, vec![0.0]);
+            assert!(!step.dones[0]);
+        }
+        let step = env.step(&[0]);
+        assert_eq!(step.rewards, vec![-1.0]);
+        assert!(step.dones[0] && env.is_done());
+        assert_eq!(step.info.turn_result, TurnResult::GameOver);
+        assert!(!step.info.truncated);
+        assert_eq!(step.observations.len(), 1);
+        assert_eq!(step.observations[0].len(), env.config().observation_len());
+        // a new episode starts from scratch
+        let observations = env.reset(0);
+        assert!(!env.is_done());
+        assert_eq!(observations, Env::new(empty_grid()).unwrap().observations());
+    }
+
+    #[test]
+    fn episodes_are_truncated() {
+        let mut env = Env::new(EnvConfig { max_steps: 3, rewards: RewardShaping { step: -0.1, ..RewardShaping::default() }, ..empty_grid() }).unwrap();
+        // turning left, then right, then left again
+        assert_eq!(env.step(&[1]).rewards, vec![-0.1]);
+        assert!(!env.step(&[2]).info.truncated);
+        let step = env.step(&[1]);
+        assert!(step.info.truncated && step.dones[0]);
+        assert_eq!(step.info.turn_result, TurnResult::Ok);
+        assert_eq!(env.game.world.snakes[0].head, Pos::new(2, 4));
+    }
+
+    #[test]
+    fn episodes_only_depend_on_the_seed() {
+        let config = EnvConfig { stones: 10, ..EnvConfig::default() };
+        let mut a = Env::new(config.clone()).unwrap();
+        let mut b = Env::new(config).unwrap();
+        assert_eq!(a.reset(7), b.reset(7));
+        assert_ne!(a.reset(7), b.reset(8));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::model::{World, Cell, Orientation, Vector, Player};
+use crate::ai::{ObservedCell, Observation, PathFinder};
+
+/// Number of feature planes, see `plane`.
+pub const PLANES: usize = 9;
+/// Length of the hand-crafted feature vector: danger and food for each of the four directions
+/// and the length of the snake.
+pub const HAND_CRAFTED_FEATURES: usize = 9;
+
+/// How a world is turned into numbers for a player.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Features {
+    /// one `rows x cols` plane per kind of cell, see `plane`
+    Planes,
+    /// danger and direction to food for each direction, length
+    HandCrafted
+}
+
+/// Plane of a cell kind and the value set in it: empty, food (growth value), stone, own head,
+/// body and tail, enemy head, body and tail. Cells outside of the grid are in no plane.
+pub fn plane(cell: ObservedCell) -> Option<(usize, f32)> {
+    match cell {
+        ObservedCell::Empty => Some((0, 1.0)),
+        ObservedCell::Food(growth_value) => Some((1, growth_value as f32)),
+        ObservedCell::Stone => Some((2, 1.0)),
+        ObservedCell::OwnHead => Some((3, 1.0)),
+        ObservedCell::OwnBody => Some((4, 1.0)),
+        ObservedCell::OwnTail => Some((5, 1.0)),
+        ObservedCell::EnemyHead => Some((6, 1.0)),
+        ObservedCell::EnemyBody => Some((7, 1.0)),
+        ObservedCell::EnemyTail => Some((8, 1.0)),
+        ObservedCell::Wall => None
+    }
+}
+
+impl Features {
+    /// Number of values for a world of the given size.
+    pub fn len(&self, rows: usize, cols: usize) -> usize {
+        match self {
+            Features::Planes => PLANES * rows * cols,
+            Features::HandCrafted => HAND_CRAFTED_FEATURES
+        }
+    }
+    pub fn encode(&self, world: &World, player: Player) -> Vec<f32> {
+        let mut out = vec![0.0; self.len(world.grid.rows(), world.grid.cols())];
+        self.encode_into(world, player, &mut out);
+        out
+    }
+    /// Writes the features of `world` from the point of view of `player` to `out`, which must
+    /// have length `len`.
+    pub fn encode_into(&self, world: &World, player: Player, out: &mut [f32]) {
+        match self {
+            Features::Planes => encode_planes(world, player, out),
+            Features::HandCrafted => encode_hand_crafted(world, player, out)
+        }
+    }
+}
+
+/// Planes in the order of `plane`, each of them row-major.
+fn encode_planes(world: &World, player: Player, out: &mut [f32]) {
+    let cells = world.grid.rows() * world.grid.cols();
+    debug_assert_eq!(out.len(), PLANES * cells);
+    out.iter_mut().for_each(|x| *x = 0.0);
+    for (pos, _) in world.grid.iter() {
+        if let Some((p, value)) = plane(ObservedCell::from_world(world, player, pos)) {
+            out[p*cells + world.grid.index(pos)] = value;
+        }
+    }
+}
+
+/// Planes of an observation window, like `Features::Planes` for a `size x size` world. Cells
+/// behind walls are all zero.
+pub fn encode_observation(observation: &Observation, out: &mut [f32]) {
+    let cells = observation.size() * observation.size();
+    debug_assert_eq!(out.len(), PLANES * cells);
+    out.iter_mut().for_each(|x| *x = 0.0);
+    for (pos, cell) in observation.cells.iter() {
+        if let Some((p, value)) = plane(*cell) {
+            out[p*cells + observation.cells.index(pos)] = value;
+        }
+    }
+}
+
+/// For up, down, left and right: 1 if moving there is deadly; then 1 for each direction that
+/// leads closer to the nearest food; then the length of the snake.
+fn encode_hand_crafted(world: &World, player: Player, out: &mut [f32]) {
+    debug_assert_eq!(out.len(), HAND_CRAFTED_FEATURES);
+    let head = world.snakes[player].head;
+    let paths = PathFinder::new(world);
+    for (i, direction) in Orientation::all().iter().enumerate() {
+        let free = world.step(head, *direction).is_some_and(|next| paths.is_free(next, 1));
+        out[i] = if free { 0.0 } else { 1.0 };
+    }
+    let food = nearest_food(world, player);
+    for (i, direction) in Orientation::all().iter().enumerate() {
+        let closer = match (food, direction) {
+            (Some(v), Orientation::Up) => v.row < 0,
+            (Some(v), Orientation::Down) => v.row > 0,
+            (Some(v), Orientation::Left) => v.col < 0,
+            (Some(v), Orientation::Right) => v.col > 0,
+            (None, _) => false
+        };
+        out[4+i] = if closer { 1.0 } else { 0.0 };
+    }
+    out[8] = world.snakes[player].length as f32;
+}
+
+/// Shortest displacement from the head of `player` to the nearest food on an empty grid.
+pub(crate) fn nearest_food(world: &World, player: Player) -> Option<Vector> {
+    let topology = world.topology();
+    let head = world.snakes[player].head;
+    world.grid.iter()
+        .filter(|(_, cell)| matches!(cell, Cell::Food(_)))
+        .map(|(pos, _)| topology.vector(head, pos))
+        .min_by_key(|v| v.manhattan_length())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::ObservationBuilder;
+
+    /// 5x5 world with walls, snake 0 at the top heading down, snake 1 to its right and food in
+    /// the bottom right corner.
+    fn world() -> World {
+        let mut world = World::with_seed(5, 5, 1);
+        world.wall_collision = true;
+        world.add_snake((1, 1), Orientation::Down).unwrap();
+        world.add_snake((1, 3), Orientation::Down).unwrap();
+        world.grid.set((4, 4), Cell::Food(3));
+        world
+    }
+
+    #[test]
+    fn every_cell_is_in_one_plane() {
+        let world = world();
+        let features = Features::Planes;
+        let out = features.encode(&world, 0);
+        assert_eq!(out.len(), features.len(5, 5));
+        let value = |p: usize, pos: (usize, usize)| out[p*25 + world.grid.index(pos)];
+        assert_eq!(value(1, (4, 4)), 3.0);
+        assert_eq!(value(3, (1, 1)), 1.0);
+        assert_eq!(value(5, (0, 1)), 1.0);
+        assert_eq!(value(6, (1, 3)), 1.0);
+        assert_eq!(value(8, (0, 3)), 1.0);
+        assert_eq!(value(0, (2, 2)), 1.0);
+        for (pos, _) in world.grid.iter() {
+            let planes = (0..PLANES).filter(|p| value(*p, (pos.row, pos.col)) != 0.0).count();
+            assert_eq!(planes, 1, "cell {:?}", pos);
+        }
+        // the other snake sees itself as its own snake
+        assert_eq!(features.encode(&world, 1)[3*25 + world.grid.index((1, 3))], 1.0);
+    }
+
+    #[test]
+    fn hand_crafted_features() {
+        let mut world = world();
+        let out = Features::HandCrafted.encode(&world, 0);
+        // up is the own tail, the others are free
+        assert_eq!(&out[0..4], &[1.0, 0.0, 0.0, 0.0]);
+        // the food is down and to the right
+        assert_eq!(&out[4..8], &[0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(out[8], 2.0);
+        // without food, no direction leads closer
+        world.grid.set((4, 4), Cell::Empty);
+        assert_eq!(nearest_food(&world, 0), None);
+        assert_eq!(&Features::HandCrafted.encode(&world, 0)[4..8], &[0.0; 4]);
+        // on the left edge, left is deadly
+        let mut world = World::with_seed(5, 5, 1);
+        world.wall_collision = true;
+        world.add_snake((1, 0), Orientation::Down).unwrap();
+        assert_eq!(&Features::HandCrafted.encode(&world, 0)[0..4], &[1.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn observation_planes_follow_the_window() {
+        let world = world();
+        let observation = ObservationBuilder::new(3).observe(&world, 0);
+        let mut out = vec![0.0; PLANES * 9];
+        encode_observation(&observation, &mut out);
+        assert_eq!(out[3*9 + 4], 1.0);
+        assert_eq!(out[5*9 + 1], 1.0);
+        assert_eq!((0..PLANES).map(|p| out[p*9 + 4]).sum::<f32>(), 1.0);
+        // cells behind the wall are in no plane
+        let mut world = World::with_seed(5, 5, 1);
+        world.wall_collision = true;
+        world.add_snake((1, 0), Orientation::Down).unwrap();
+        let observation = ObservationBuilder::new(3).observe(&world, 0);
+        encode_observation(&observation, &mut out);
+        assert_eq!((0..PLANES).map(|p| out[p*9 + 3]).sum::<f32>(), 0.0);
+    }
+}
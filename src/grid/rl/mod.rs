@@ -0,0 +1,9 @@
+mod features;
+mod env;
+mod vec_env;
+mod tabular;
+
+pub use features::*;
+pub use env::*;
+pub use vec_env::*;
+pub use tabular::*;
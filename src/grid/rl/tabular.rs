@@ -0,0 +1,307 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+use crate::model::{Game, World, Orientation, PlayerInput, Player, Vector, TurnResult};
+use crate::ai::{Agent, PathFinder};
+use super::{Env, ActionSpace, nearest_food};
+
+const HEADER: &str = "snakes-q-table 1";
+
+/// Number of states of `state`.
+pub const TABULAR_STATES: usize = 512;
+/// Relative actions: straight on, turn left, turn right, like `ActionSpace::Relative`.
+pub const TABULAR_ACTIONS: usize = 3;
+
+/// Compact state of `player` for tabular learning, below `TABULAR_STATES`.
+///
+/// Bits 0 to 2: moving straight on, left or right is deadly. Bits 3 to 6: food lies ahead, to
+/// the left, to the right or behind. Bits 7 and 8: the heading.
+pub fn state(world: &World, player: Player) -> usize {
+    let head = world.snakes[player].head;
+    let heading = world.snake_direction(head);
+    let paths = PathFinder::new(world);
+    let mut state = 0;
+    for (i, direction) in [heading, heading.counter_clockwise(), heading.clockwise()].iter().enumerate() {
+        let free = world.step(head, *direction).is_some_and(|next| paths.is_free(next, 1));
+        if !free {
+            state |= 1 << i;
+        }
+    }
+    if let Some(food) = nearest_food(world, player) {
+        for (i, direction) in [heading, heading.counter_clockwise(), heading.clockwise(), heading.opposite()].iter().enumerate() {
+            let towards = Vector::from(*direction);
+            if food.row * towards.row + food.col * towards.col > 0 {
+                state |= 1 << (3 + i);
+            }
+        }
+    }
+    let heading_index = Orientation::all().iter().position(|d| *d == heading).unwrap();
+    state | heading_index << 7
+}
+
+/// Estimated returns of every action in every state.
+///
+/// Saved as text: a header line, then one line per state with the values of its actions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QTable {
+    pub values: Vec<[f32; TABULAR_ACTIONS]>
+}
+
+impl Default for QTable {
+    fn default() -> QTable {
+        QTable { values: vec![[0.0; TABULAR_ACTIONS]; TABULAR_STATES] }
+    }
+}
+
+impl QTable {
+    pub fn new() -> QTable {
+        QTable::default()
+    }
+    /// Loads a table, an untrained one if the file doesn't exist yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<QTable> {
+        match File::open(path) {
+            Ok(file) => QTable::read(BufReader::new(file)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(QTable::new()),
+            Err(e) => Err(e)
+        }
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+    pub fn read<R: BufRead>(input: R) -> io::Result<QTable> {
+        let mut lines = input.lines();
+        let header = lines.next().transpose()?;
+        if header.as_deref().map(str::trim) != Some(HEADER) {
+            return Err(invalid(1, "not a q-table"));
+        }
+        let mut values = Vec::with_capacity(TABULAR_STATES);
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut actions = [0.0; TABULAR_ACTIONS];
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != TABULAR_ACTIONS {
+                return Err(invalid(i+2, &format!("expected {} values", TABULAR_ACTIONS)));
+            }
+            for (value, field) in actions.iter_mut().zip(fields) {
+                *value = field.parse().map_err(|_| invalid(i+2, &format!("invalid number '{}'", field)))?;
+            }
+            values.push(actions);
+        }
+        if values.len() != TABULAR_STATES {
+            return Err(invalid(values.len()+1, &format!("expected {} states", TABULAR_STATES)));
+        }
+        Ok(QTable { values })
+    }
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        for actions in &self.values {
+            let actions: Vec<String> = actions.iter().map(|value| value.to_string()).collect();
+            writeln!(out, "{}", actions.join(" "))?;
+        }
+        Ok(())
+    }
+    /// Action with the highest value in `state`, the first one of equal ones.
+    pub fn best_action(&self, state: usize) -> usize {
+        let actions = &self.values[state];
+        (0..TABULAR_ACTIONS).fold(0, |best, a| if actions[a] > actions[best] { a } else { best })
+    }
+    pub fn max_value(&self, state: usize) -> f32 {
+        self.values[state][self.best_action(state)]
+    }
+}
+
+fn invalid(line: usize, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
+
+/// How the value of the next state is estimated in an update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TdAlgorithm {
+    /// by the best action, off-policy
+    QLearning,
+    /// by the action taken next, on-policy
+    Sarsa
+}
+
+impl FromStr for TdAlgorithm {
+    type Err = ();
+    fn from_str(s: &str) -> Result<TdAlgorithm, ()> {
+        match s {
+            "q-learning" => Ok(TdAlgorithm::QLearning),
+            "sarsa" => Ok(TdAlgorithm::Sarsa),
+            _ => Err(())
+        }
+    }
+}
+
+/// Sum of rewards, final length and number of steps of an episode.
+#[derive(Debug, Clone, Copy)]
+pub struct EpisodeStats {
+    pub reward: f32,
+    pub length: usize,
+    pub steps: usize
+}
+
+/// Learns a `QTable` for the first snake of an `Env` by temporal difference learning, exploring
+/// epsilon-greedily.
+pub struct TabularLearner {
+    pub table: QTable,
+    pub algorithm: TdAlgorithm,
+    /// learning rate
+    pub alpha: f32,
+    /// discount of future rewards
+    pub gamma: f32,
+    /// probability of a random action
+    pub epsilon: f32,
+    rng: SmallRng
+}
+
+impl TabularLearner {
+    pub fn new(table: QTable, algorithm: TdAlgorithm, seed: u64) -> TabularLearner {
+        TabularLearner { table, algorithm, alpha: 0.1, gamma: 0.9, epsilon: 0.1, rng: SmallRng::seed_from_u64(seed) }
+    }
+    fn choose(&mut self, state: usize) -> usize {
+        if self.rng.gen::<f32>() < self.epsilon {
+            self.rng.gen_range(0, TABULAR_ACTIONS)
+        } else {
+            self.table.best_action(state)
+        }
+    }
+    /// Plays an episode from `env.reset(seed)` and updates the table after every step. `env`
+    /// needs relative actions.
+    pub fn train_episode(&mut self, env: &mut Env, seed: u64) -> EpisodeStats {
+        debug_assert_eq!(env.config().action_space, ActionSpace::Relative);
+        env.reset(seed);
+        let mut s = state(&env.game.world, 0);
+        let mut a = self.choose(s);
+        let mut stats = EpisodeStats { reward: 0.0, length: env.game.world.snakes[0].length, steps: 0 };
+        loop {
+            let (rewards, info) = env.advance(&[a]);
+            stats.reward += rewards[0];
+            stats.length = info.lengths[0];
+            stats.steps += 1;
+            let world = &env.game.world;
+            // an episode cut off by the step limit goes on as far as the values are concerned
+            let terminal = !world.snakes[0].alive || world.turn_result != TurnResult::Ok;
+            let mut next = (s, a);
+            let target = if terminal {
+                rewards[0]
+            } else {
+                let s2 = state(world, 0);
+                next = (s2, self.choose(s2));
+                rewards[0] + self.gamma * match self.algorithm {
+                    TdAlgorithm::QLearning => self.table.max_value(next.0),
+                    TdAlgorithm::Sarsa => self.table.values[next.0][next.1]
+                }
+            };
+            let value = &mut self.table.values[s][a];
+            *value += self.alpha * (target - *value);
+            if env.dones()[0] {
+                return stats;
+            }
+            (s, a) = next;
+        }
+    }
+}
+
+/// Plays greedily by a learned `QTable`.
+pub struct QAgent {
+    /// the snake to control
+    pub player: Player,
+    pub table: QTable
+}
+
+impl QAgent {
+    pub fn new(player: Player, table: QTable) -> QAgent {
+        QAgent { player, table }
+    }
+}
+
+impl Agent for QAgent {
+    fn decide(&mut self, game: &Game) -> PlayerInput {
+        if !game.world.snakes[self.player].alive {
+            return PlayerInput::DoNothing;
+        }
+        ActionSpace::Relative.input(self.table.best_action(state(&game.world, self.player)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Cell;
+    use crate::rl::EnvConfig;
+
+    fn text(table: &QTable) -> String {
+        let mut out = Vec::new();
+        table.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn parse_error(text: &str) -> String {
+        let e = QTable::read(text.as_bytes()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        e.to_string()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut table = QTable::new();
+        table.values[0] = [0.5, -1.25, 3.0];
+        table.values[TABULAR_STATES-1] = [1e-7, 0.1, -0.3];
+        let read = QTable::read(text(&table).as_bytes()).unwrap();
+        assert_eq!(read, table);
+        assert_eq!(read.best_action(0), 2);
+        assert_eq!(read.max_value(0), 3.0);
+        // ties go to the first action
+        assert_eq!(read.best_action(1), 0);
+    }
+
+    #[test]
+    fn broken_tables_are_rejected() {
+        let table = text(&QTable::new());
+        assert_eq!(parse_error("snakes-leaderboard 1\n"), "line 1: not a q-table");
+        let short = table.replacen("0 0 0\n", "", 1);
+        assert_eq!(parse_error(&short), "line 512: expected 512 states");
+        assert_eq!(parse_error(&table.replacen("0 0 0", "0 0", 1)), "line 2: expected 3 values");
+        assert_eq!(parse_error(&table.replacen("0 0 0", "0 x 0", 1)), "line 2: invalid number 'x'");
+    }
+
+    #[test]
+    fn state_describes_danger_and_food() {
+        let mut world = World::with_seed(5, 5, 1);
+        world.wall_collision = true;
+        // on the left edge heading down: right of the snake is the wall, food straight ahead
+        world.add_snake((1, 0), Orientation::Down).unwrap();
+        world.grid.set((4, 0), Cell::Food(1));
+        let heading = Orientation::all().iter().position(|d| *d == Orientation::Down).unwrap();
+        assert_eq!(state(&world, 0), 1 << 2 | 1 << 3 | heading << 7);
+    }
+
+    #[test]
+    fn learning_improves_on_an_untrained_table() {
+        let config = EnvConfig { rows: 6, cols: 6, stones: 0, max_steps: 100, ..EnvConfig::default() };
+        let mut env = Env::new(config).unwrap();
+        let mut learner = TabularLearner::new(QTable::new(), TdAlgorithm::QLearning, 1);
+        let total = |learner: &mut TabularLearner, env: &mut Env, episodes: std::ops::Range<u64>| {
+            episodes.map(|seed| learner.train_episode(env, seed).reward).sum::<f32>()
+        };
+        let first = total(&mut learner, &mut env, 0..100);
+        for _ in 0..5 {
+            total(&mut learner, &mut env, 100..200);
+        }
+        learner.epsilon = 0.0;
+        assert!(total(&mut learner, &mut env, 200..300) > first);
+        assert_ne!(learner.table, QTable::new());
+    }
+}
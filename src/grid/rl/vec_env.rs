@@ -0,0 +1,253 @@
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+
+use super::{Env, EnvConfig};
+
+/// A finished episode of one of the environments of a `VecEnv`.
+#[derive(Debug, Clone)]
+pub struct Episode {
+    pub env: usize,
+    pub steps: usize,
+    /// sum of the rewards of each player
+    pub returns: Vec<f32>,
+    /// length of each snake at the end
+    pub lengths: Vec<usize>
+}
+
+enum Command {
+    Reset(u64),
+    Step(Vec<usize>)
+}
+
+/// Results of the environments of one worker, in the layout of the `VecEnv` buffers.
+struct Chunk {
+    worker: usize,
+    observations: Vec<f32>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+    episodes: Vec<Episode>
+}
+
+/// An environment of a worker, with the statistics of its running episode.
+struct Slot {
+    env: Env,
+    index: usize,
+    episode: u64,
+    steps: usize,
+    returns: Vec<f32>
+}
+
+struct Worker {
+    commands: Sender<Command>,
+    first_env: usize,
+    envs: usize,
+    thread: Option<thread::JoinHandle<()>>
+}
+
+/// Runs many independent `Env`s on a fixed set of threads and steps them all at once.
+///
+/// Observations, rewards and done flags are kept in contiguous buffers, ordered by environment
+/// and then by player. Finished episodes are restarted right away: their done flags are set and
+/// the observations are already the ones of the new episode.
+pub struct VecEnv {
+    config: EnvConfig,
+    num_envs: usize,
+    workers: Vec<Worker>,
+    results: Receiver<Chunk>,
+    observations: Vec<f32>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>
+}
+
+impl VecEnv {
+    /// `num_envs` environments with the same `config` on `threads` threads. Fails if `config`
+    /// doesn't pass `EnvConfig::validate`.
+    pub fn new(config: EnvConfig, num_envs: usize, threads: usize) -> Result<VecEnv, String> {
+        debug_assert!(num_envs > 0 && threads > 0);
+        config.validate()?;
+        let threads = threads.min(num_envs);
+        let (results_sender, results) = channel();
+        let mut workers = Vec::new();
+        let mut first_env = 0;
+        for worker in 0..threads {
+            // spread the remainder over the first workers
+            let envs = num_envs / threads + if worker < num_envs % threads { 1 } else { 0 };
+            let (commands, command_receiver) = channel();
+            let results = results_sender.clone();
+            let config = config.clone();
+            let thread = thread::spawn(move || run_worker(worker, config, first_env, envs, num_envs, command_receiver, results));
+            workers.push(Worker { commands, first_env, envs, thread: Some(thread) });
+            first_env += envs;
+        }
+        let players = config.snakes;
+        Ok(VecEnv {
+            observations: vec![0.0; num_envs * players * config.observation_len()],
+            rewards: vec![0.0; num_envs * players],
+            dones: vec![false; num_envs * players],
+            config,
+            num_envs,
+            workers,
+            results
+        })
+    }
+    pub fn num_envs(&self) -> usize {
+        self.num_envs
+    }
+    pub fn players(&self) -> usize {
+        self.config.snakes
+    }
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+    /// Starts new episodes in all environments. Episode `e` of environment `i` is played with
+    /// seed `seed + i + e * num_envs`, independent of the number of threads.
+    pub fn reset(&mut self, seed: u64) -> &[f32] {
+        for worker in &self.workers {
+            worker.commands.send(Command::Reset(seed)).expect("worker stopped");
+        }
+        self.collect();
+        &self.observations
+    }
+    /// Steps all environments, `actions` holds one action per environment and player. Returns
+    /// the episodes that finished.
+    pub fn step(&mut self, actions: &[usize]) -> Vec<Episode> {
+        let players = self.players();
+        debug_assert_eq!(actions.len(), self.num_envs * players);
+        for worker in &self.workers {
+            let range = worker.first_env * players..(worker.first_env + worker.envs) * players;
+            worker.commands.send(Command::Step(actions[range].to_vec())).expect("worker stopped");
+        }
+        self.collect()
+    }
+    /// Observations of all environments and players, each `config().observation_len()` long.
+    pub fn observations(&self) -> &[f32] {
+        &self.observations
+    }
+    /// Rewards of the last step.
+    pub fn rewards(&self) -> &[f32] {
+        &self.rewards
+    }
+    /// Whether the episode ended in the last step.
+    pub fn dones(&self) -> &[bool] {
+        &self.dones
+    }
+    /// Waits for all workers and copies their results into the buffers.
+    fn collect(&mut self) -> Vec<Episode> {
+        let players = self.players();
+        let observation_len = self.config.observation_len() * players;
+        let mut episodes = Vec::new();
+        for _ in 0..self.workers.len() {
+            let chunk = self.results.recv().expect("worker stopped");
+            let first = self.workers[chunk.worker].first_env;
+            let envs = self.workers[chunk.worker].envs;
+            self.observations[first*observation_len..(first+envs)*observation_len].copy_from_slice(&chunk.observations);
+            self.rewards[first*players..(first+envs)*players].copy_from_slice(&chunk.rewards);
+            self.dones[first*players..(first+envs)*players].copy_from_slice(&chunk.dones);
+            episodes.extend(chunk.episodes);
+        }
+        episodes.sort_by_key(|episode| episode.env);
+        episodes
+    }
+}
+
+impl Drop for VecEnv {
+    fn drop(&mut self) {
+        for worker in &mut self.workers {
+            // closing the channel stops the worker
+            let (closed, _) = channel();
+            worker.commands = closed;
+            if let Some(thread) = worker.thread.take() {
+                thread.join().ok();
+            }
+        }
+    }
+}
+
+fn run_worker(worker: usize, config: EnvConfig, first_env: usize, envs: usize, num_envs: usize,
+              commands: Receiver<Command>, results: Sender<Chunk>) {
+    let players = config.snakes;
+    let observation_len = config.observation_len();
+    let mut slots: Vec<Slot> = (first_env..first_env+envs)
+        .map(|index| Slot { env: Env::new(config.clone()).expect("config was validated"), index, episode: 0, steps: 0, returns: vec![0.0; players] })
+        .collect();
+    let mut seed = 0;
+    while let Ok(command) = commands.recv() {
+        let mut chunk = Chunk {
+            worker,
+            observations: vec![0.0; envs * players * observation_len],
+            rewards: vec![0.0; envs * players],
+            dones: vec![false; envs * players],
+            episodes: Vec::new()
+        };
+        for (i, slot) in slots.iter_mut().enumerate() {
+            match &command {
+                Command::Reset(base) => {
+                    seed = *base;
+                    slot.episode = 0;
+                    slot.restart(seed, num_envs);
+                },
+                Command::Step(actions) => {
+                    let (rewards, info) = slot.env.advance(&actions[i*players..(i+1)*players]);
+                    slot.steps += 1;
+                    for (s, reward) in rewards.iter().enumerate() {
+                        slot.returns[s] += reward;
+                    }
+                    chunk.rewards[i*players..(i+1)*players].copy_from_slice(&rewards);
+                    chunk.dones[i*players..(i+1)*players].copy_from_slice(slot.env.dones());
+                    if slot.env.is_done() {
+                        chunk.episodes.push(Episode { env: slot.index, steps: slot.steps, returns: slot.returns.clone(), lengths: info.lengths });
+                        slot.episode += 1;
+                        slot.restart(seed, num_envs);
+                    }
+                }
+            }
+            for s in 0..players {
+                let offset = (i*players + s) * observation_len;
+                slot.env.observe(s, &mut chunk.observations[offset..offset+observation_len]);
+            }
+        }
+        if results.send(chunk).is_err() {
+            return;
+        }
+    }
+}
+
+impl Slot {
+    fn restart(&mut self, seed: u64, num_envs: usize) {
+        let episode_seed = seed.wrapping_add(self.index as u64).wrapping_add(self.episode.wrapping_mul(num_envs as u64));
+        self.env.reset(episode_seed);
+        self.steps = 0;
+        self.returns.iter_mut().for_each(|r| *r = 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vec_env_matches_single_envs() {
+        let config = EnvConfig { stones: 10, max_steps: 5, ..EnvConfig::default() };
+        let mut envs: Vec<Env> = (0..3).map(|_| Env::new(config.clone()).unwrap()).collect();
+        let mut vec_env = VecEnv::new(config.clone(), 3, 2).unwrap();
+        let mut single = VecEnv::new(config, 3, 1).unwrap();
+        let observations: Vec<f32> = envs.iter_mut().enumerate().flat_map(|(i, env)| env.reset(10 + i as u64).concat()).collect();
+        assert_eq!(vec_env.reset(10), &observations[..]);
+        assert_eq!(single.reset(10), &observations[..]);
+        let actions = [1, 2, 0];
+        for step in 0..5 {
+            let finished = vec_env.step(&actions);
+            assert_eq!(single.step(&actions).len(), finished.len());
+            let rewards: Vec<f32> = envs.iter_mut().zip(&actions).flat_map(|(env, action)| env.step(&[*action]).rewards).collect();
+            assert_eq!(vec_env.rewards(), &rewards[..]);
+            assert_eq!(vec_env.dones(), single.dones());
+            if step == 4 {
+                // all episodes are truncated, the next ones already started
+                assert_eq!(finished.iter().map(|episode| episode.env).collect::<Vec<_>>(), vec![0, 1, 2]);
+                assert!(finished.iter().all(|episode| episode.steps == 5));
+                let next: Vec<f32> = envs.iter_mut().enumerate().flat_map(|(i, env)| env.reset(13 + i as u64).concat()).collect();
+                assert_eq!(vec_env.observations(), &next[..]);
+            }
+        }
+    }
+}
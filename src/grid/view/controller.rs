@@ -1,7 +1,12 @@
 use std::{thread, time};
+use std::collections::VecDeque;
 
 use crate::model::{Game, TurnResult, PlayerInput, World};
+use crate::replay::Replay;
+use crate::ai::Agent;
 
+/// Direction changes a player can enter ahead of time.
+const MAX_QUEUED_INPUTS: usize = 3;
 
 pub trait View {
     fn read_user_inputs(&mut self) -> Vec<UserAction>;
@@ -10,42 +15,84 @@ pub trait View {
 
 pub enum UserAction {
     Quit,
-    Player(usize, PlayerInput)
+    Player(usize, PlayerInput),
+    TogglePause,
+    Step,
+    SpeedUp,
+    SlowDown
 }
 
 pub struct Controller<V: View + Sized> {
     pub game: Game,
     pub view: V,
     pub quit_on_game_over: bool,
-    pub step_interval: time::Duration
+    pub step_interval: time::Duration,
+    /// turns played by `run_loop` are appended, if set
+    pub replay: Option<Replay>,
+    /// pending inputs per player, one is used per step
+    input_queues: Vec<VecDeque<PlayerInput>>,
+    /// snakes controlled by an agent instead of the keyboard
+    agents: Vec<(usize, Box<dyn Agent>)>
 }
 
 
 impl<V: View + Sized> Controller<V> {
     pub fn new(game: Game, view: V) -> Controller<V> {
         Controller {
-            game: game,
-            view: view,
+            game,
+            view,
             quit_on_game_over: true,
-            step_interval: time::Duration::from_millis(1000/2)
+            step_interval: time::Duration::from_millis(1000/2),
+            replay: None,
+            input_queues: Vec::new(),
+            agents: Vec::new()
         }
     }
+    /// Lets `agent` control snake `player`, it's asked for an input every step.
+    pub fn add_agent(&mut self, player: usize, agent: Box<dyn Agent>) {
+        self.agents.retain(|(p, _)| *p != player);
+        self.agents.push((player, agent));
+    }
+    /// Records the game from now on, see `replay`.
+    pub fn record(&mut self) {
+        self.replay = Some(Replay::start(&mut self.game));
+    }
     pub fn run_loop(&mut self){
         self.view.read_user_inputs(); // drop any user input
         self.view.draw_world(&self.game.world);
-        thread::sleep(time::Duration::from_millis(1000/1));
-        let mut directions = vec![PlayerInput::DoNothing; self.game.world.player_count()];
+        thread::sleep(time::Duration::from_millis(1000));
+        self.input_queues = vec![VecDeque::new(); self.game.world.player_count()];
         loop{
             // read world state
             let actions = self.view.read_user_inputs();
             for a in actions {
                 match a {
                     UserAction::Quit => return,
-                    UserAction::Player(pid, dir) => if pid < directions.len() {directions[pid] = dir}
+                    // further keys are ignored while the queue is full
+                    UserAction::Player(pid, dir) if pid < self.input_queues.len() && self.input_queues[pid].len() < MAX_QUEUED_INPUTS
+                        && !self.agents.iter().any(|(p, _)| *p == pid) => {
+                        self.input_queues[pid].push_back(dir);
+                    },
+                    _ => ()
                 }
             }
             // run game step
+            let mut directions: Vec<PlayerInput> = (0..self.input_queues.len()).map(|pid| self.next_input(pid)).collect();
+            for (pid, agent) in self.agents.iter_mut() {
+                if *pid < directions.len() && self.game.world.snakes[*pid].alive {
+                    directions[*pid] = agent.decide(&self.game.observed_by(*pid));
+                }
+            }
             self.game.advance(&directions);
+            if let Some(replay) = &mut self.replay {
+                replay.push_turn(&directions, self.game.world.hash());
+            }
+            if cfg!(debug_assertions) {
+                if let Err(violations) = self.game.world.validate() {
+                    let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                    panic!("inconsistent world: {}", messages.join(", "));
+                }
+            }
             let turn_result = self.game.world.turn_result;
             // Display on screen
             self.view.draw_world(&self.game.world);
@@ -60,5 +107,82 @@ impl<V: View + Sized> Controller<V> {
             thread::sleep(self.step_interval);
         } 
     }
+    /// Takes the next queued input of `player` that changes its heading. Inputs that keep the
+    /// heading or would reverse the snake are dropped.
+    fn next_input(&mut self, player: usize) -> PlayerInput {
+        let snake = self.game.world.snakes[player];
+        if !snake.alive {
+            self.input_queues[player].clear();
+            return PlayerInput::DoNothing;
+        }
+        let heading = self.game.world.snake_direction(snake.head);
+        while let Some(input) = self.input_queues[player].pop_front() {
+            let direction = input.orientation(heading);
+            if direction != heading && direction != heading.opposite() {
+                return input;
+            }
+        }
+        PlayerInput::DoNothing
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Orientation, Pos};
+
+    /// Hands out scripted key presses, one batch per read, and remembers where the head of the
+    /// first snake was drawn.
+    struct ScriptedView {
+        frames: VecDeque<Vec<UserAction>>,
+        heads: Vec<Pos>
+    }
+
+    impl View for ScriptedView {
+        fn read_user_inputs(&mut self) -> Vec<UserAction> {
+            self.frames.pop_front().unwrap_or_else(|| vec![UserAction::Quit])
+        }
+        fn draw_world(&mut self, world: &World) {
+            self.heads.push(world.snakes[0].head);
+        }
+    }
+
+    fn controller(frames: Vec<Vec<UserAction>>) -> Controller<ScriptedView> {
+        let mut world = World::with_seed(10, 10, 1);
+        world.add_snake((1, 2), Orientation::Down).unwrap();
+        let mut game = Game::new(world);
+        game.max_snacks = 0;
+        let view = ScriptedView { frames: frames.into(), heads: Vec::new() };
+        let mut controller = Controller::new(game, view);
+        controller.step_interval = time::Duration::from_millis(0);
+        controller
+    }
+
+    #[test]
+    fn queued_inputs_are_used_one_per_step() {
+        use Orientation::*;
+        let keys = [Left, Up, Right, Down].iter().map(|d| UserAction::Player(0, PlayerInput::Go(*d))).collect();
+        // the first read only drops old key presses
+        let mut controller = controller(vec![Vec::new(), keys, Vec::new(), Vec::new(), Vec::new()]);
+        controller.run_loop();
+        // down didn't fit into the queue anymore, after the queue runs empty the snake keeps going
+        assert_eq!(controller.view.heads, vec![Pos::new(1, 2), Pos::new(1, 1), Pos::new(0, 1), Pos::new(0, 2), Pos::new(0, 3)]);
+    }
+
+    #[test]
+    fn useless_inputs_are_skipped() {
+        let mut controller = controller(Vec::new());
+        controller.input_queues = vec![VecDeque::new()];
+        // the snake heads down, down keeps the heading and up would reverse it
+        let queued = [PlayerInput::Go(Orientation::Down), PlayerInput::Go(Orientation::Up), PlayerInput::TurnLeft, PlayerInput::Go(Orientation::Left)];
+        controller.input_queues[0].extend(queued.iter());
+        assert_eq!(controller.next_input(0), PlayerInput::TurnLeft);
+        assert_eq!(controller.next_input(0), PlayerInput::Go(Orientation::Left));
+        assert_eq!(controller.next_input(0), PlayerInput::DoNothing);
+        // dead snakes drop their inputs
+        controller.input_queues[0].push_back(PlayerInput::TurnRight);
+        controller.game.world.snakes[0].alive = false;
+        assert_eq!(controller.next_input(0), PlayerInput::DoNothing);
+        assert!(controller.input_queues[0].is_empty());
+    }
+}
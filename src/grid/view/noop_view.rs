@@ -2,6 +2,7 @@ use crate::model::*;
 
 use super::controller::{View, UserAction};
 
+#[derive(Default)]
 pub struct NoopView {}
 
 /// View that just throws away any command. For headless mode.
@@ -15,6 +16,6 @@ impl View for NoopView {
     fn read_user_inputs(&mut self) -> Vec<UserAction> {
         Vec::new()
     }
-    fn draw_world(&mut self, world: &World) {}
+    fn draw_world(&mut self, _world: &World) {}
 }
 
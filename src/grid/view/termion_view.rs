@@ -11,7 +11,10 @@ use std::io::{Write, stdout, Stdout};
 pub struct TermionView {
     events: termion::input::Events<termion::AsyncReader>,
     stdout: termion::raw::RawTerminal<Stdout>,
-    players: usize
+    /// left and right keys turn the snake relative to its heading, up and down are ignored
+    pub relative_controls: bool,
+    /// under fog of war, only the cells these snakes see are drawn, all snakes' if `None`
+    pub viewers: Option<Vec<Player>>
 }
 
 impl TermionView {
@@ -23,7 +26,8 @@ impl TermionView {
         Ok(TermionView {
             events: termion::async_stdin().events(),
             stdout: std,
-            players: 2
+            relative_controls: false,
+            viewers: None
         })
     }
 }
@@ -36,28 +40,45 @@ impl TermionView {
             _ => write!(self.stdout, "{}", color::Fg(color::Reset)).unwrap()
         }
     }
+    /// Input for a direction key.
+    fn steer(&self, key: Orientation) -> PlayerInput {
+        match key {
+            _ if !self.relative_controls => PlayerInput::Go(key),
+            Orientation::Left => PlayerInput::TurnLeft,
+            Orientation::Right => PlayerInput::TurnRight,
+            _ => PlayerInput::DoNothing
+        }
+    }
+    /// Whether `pos` is hidden by the fog of war from all viewers.
+    fn in_fog(&self, world: &World, pos: Pos, cell: &Cell) -> bool {
+        let sees = |s: Player| matches!(cell, Cell::Snake(c, _) if *c == s) || world.is_visible(s, pos);
+        match &self.viewers {
+            _ if world.fog_of_war.is_none() => false,
+            Some(viewers) => !viewers.iter().any(|s| sees(*s)),
+            None => !(0..world.player_count()).any(sees)
+        }
+    }
     fn game_running(&mut self, world: &World) {
         write!(self.stdout, "{}", termion::clear::All).unwrap();
         
-        for row in 0..world.grid.rows() {
-            for col in 0..world.grid.cols() {
-                write!(self.stdout, "{}", termion::cursor::Goto((col+1) as u16, (row+1) as u16)).unwrap();
-                match world.grid.get((row, col)) {
-                    Cell::Empty => write!(self.stdout, "_").unwrap(),
-                    Cell::Food(1) => write!(self.stdout, "'").unwrap(),
-                    Cell::Food(2) => write!(self.stdout, "^").unwrap(),
-                    Cell::Food(_) => write!(self.stdout, "A").unwrap(),
-                    Cell::Stone => write!(self.stdout, "!").unwrap(),
-                    Cell::Snake(s, d) => {
-                        self.player_color(*s);
-                        if world.is_head(*s, (row, col)) {write!(self.stdout, "o").unwrap();}
-                        else if world.is_tail(*s, (row, col)) {write!(self.stdout, ".").unwrap();}
-                        else if world.is_body(*s, (row, col)) && (*d == Orientation::Up || *d == Orientation::Down) {write!(self.stdout, "|").unwrap();}
-                        else if world.is_body(*s, (row, col)) && (*d == Orientation::Left || *d == Orientation::Right) {write!(self.stdout, "=").unwrap();}
-                        write!(self.stdout, "{}", color::Fg(color::Reset)).unwrap();
-                    }
-                };
-            }
+        for (pos, cell) in world.grid.iter() {
+            write!(self.stdout, "{}", termion::cursor::Goto((pos.col+1) as u16, (pos.row+1) as u16)).unwrap();
+            match cell {
+                _ if self.in_fog(world, pos, cell) => write!(self.stdout, " ").unwrap(),
+                Cell::Empty => write!(self.stdout, "_").unwrap(),
+                Cell::Food(1) => write!(self.stdout, "'").unwrap(),
+                Cell::Food(2) => write!(self.stdout, "^").unwrap(),
+                Cell::Food(_) => write!(self.stdout, "A").unwrap(),
+                Cell::Stone => write!(self.stdout, "!").unwrap(),
+                Cell::Snake(s, d) => {
+                    self.player_color(*s);
+                    if world.is_head(*s, pos) {write!(self.stdout, "o").unwrap();}
+                    else if world.is_tail(*s, pos) {write!(self.stdout, ".").unwrap();}
+                    else if world.is_body(*s, pos) && (*d == Orientation::Up || *d == Orientation::Down) {write!(self.stdout, "|").unwrap();}
+                    else if world.is_body(*s, pos) && (*d == Orientation::Left || *d == Orientation::Right) {write!(self.stdout, "=").unwrap();}
+                    write!(self.stdout, "{}", color::Fg(color::Reset)).unwrap();
+                }
+            };
         }
 
         write!(self.stdout, "{}", termion::cursor::Goto(1, 1 + world.grid.rows() as u16 )).unwrap();
@@ -88,35 +109,33 @@ impl TermionView {
 
 impl View for TermionView {
     fn read_user_inputs(&mut self) -> Vec<UserAction> {
-        let mut dirs: Vec<Option<Orientation>> = vec![None; self.players];
         let mut quit = false;
+        let mut result = Vec::new();
         // User input
         loop {
             let event = self.events.next();
             match event {
                 None => break,
                 Some(Ok(Event::Key(e))) => match e {
-                    Key::Left => dirs[0] = Some(Orientation::Left),
-                    Key::Right => dirs[0] = Some(Orientation::Right),
-                    Key::Up => dirs[0] = Some(Orientation::Up),
-                    Key::Down => dirs[0] = Some(Orientation::Down),
-                    Key::Char('a') => dirs[1] = Some(Orientation::Left),
-                    Key::Char('d') => dirs[1] = Some(Orientation::Right),
-                    Key::Char('w') => dirs[1] = Some(Orientation::Up),
-                    Key::Char('s') => dirs[1] = Some(Orientation::Down),
+                    Key::Left => result.push(UserAction::Player(0, self.steer(Orientation::Left))),
+                    Key::Right => result.push(UserAction::Player(0, self.steer(Orientation::Right))),
+                    Key::Up => result.push(UserAction::Player(0, self.steer(Orientation::Up))),
+                    Key::Down => result.push(UserAction::Player(0, self.steer(Orientation::Down))),
+                    Key::Char('a') => result.push(UserAction::Player(1, self.steer(Orientation::Left))),
+                    Key::Char('d') => result.push(UserAction::Player(1, self.steer(Orientation::Right))),
+                    Key::Char('w') => result.push(UserAction::Player(1, self.steer(Orientation::Up))),
+                    Key::Char('s') => result.push(UserAction::Player(1, self.steer(Orientation::Down))),
                     Key::Char('q') => quit = true,
+                    Key::Char(' ') => result.push(UserAction::TogglePause),
+                    Key::Char('n') => result.push(UserAction::Step),
+                    Key::Char('+') => result.push(UserAction::SpeedUp),
+                    Key::Char('-') => result.push(UserAction::SlowDown),
                     Key::Char(_) => (),
                     _ => (),
                 },
                 _ => ()
             };
         };
-        let mut result = Vec::new();
-        for i in 0..dirs.len() {
-            if dirs[i].is_some() {
-                result.push(UserAction::Player(i, PlayerInput::Go(dirs[i].unwrap())));
-            }
-        }
         if quit {
             result.push(UserAction::Quit);
         }
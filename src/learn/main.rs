@@ -1,36 +1,180 @@
 extern crate gridsnakes;
+extern crate clap;
 
 use gridsnakes::view::{TermionView, View, UserAction};
 use gridsnakes::model::{Game, World, PlayerInput, Orientation, TurnResult};
-use gridsnakes::ai::{Agent, SpaceExplorer};
-use std::{thread, time};
+use gridsnakes::ai::Agent;
+use gridsnakes::rl::{Env, EnvConfig, EpisodeStats, QAgent, QTable, TabularLearner, TdAlgorithm};
+use gridsnakes::rating::Leaderboard;
+use clap::{App, Arg};
+use std::{process, thread, time};
 
-fn main(){
-    let size = 10;
-    let mut game = Game::new(World::new(size, size));
-    game.world.add_snake((1,1), Orientation::Down).unwrap();
-    game.world.place_stones_randomly(3);
+/// Limits of the time between two steps while watching, the speed keys stop there.
+const MIN_SLEEP_INTERVAL: time::Duration = time::Duration::from_millis(1);
+const MAX_SLEEP_INTERVAL: time::Duration = time::Duration::from_secs(10);
+
+/// Watches the learned agent play a game in the terminal, then rates it in `leaderboard_file`
+/// if given.
+fn watch(config: &EnvConfig, table: QTable, leaderboard_file: Option<&str>) {
+    let mut game = Game::new(World::new(config.rows, config.cols));
+    game.world.wall_collision = config.wall_collision;
+    game.world.add_snake((1, 2), Orientation::Down).unwrap();
+    game.world.place_stones_randomly(config.stones);
+    game.max_snacks = config.max_snacks;
     let mut view = TermionView::new().unwrap();
     let mut sleep_interval = time::Duration::from_millis(1000/10);
     view.draw_world(&game.world);
-    let mut agent = SpaceExplorer::new(6);
+    let mut agent = QAgent::new(0, table);
     loop {
         let input = vec![agent.decide(&game)];
         game.advance(&input);
-        if game.iteration() % 1 == 0 {
-            view.draw_world(&game.world);
-            thread::sleep(sleep_interval); 
-        }
+        view.draw_world(&game.world);
+        thread::sleep(sleep_interval);
         for e in view.read_user_inputs() {
             match e {
                 UserAction::Quit => return,
-                UserAction::Player(0, PlayerInput::Go(Orientation::Up)) => (sleep_interval /= 2),
-                UserAction::Player(0, PlayerInput::Go(Orientation::Down)) => (sleep_interval *= 2),
+                UserAction::Player(0, PlayerInput::Go(Orientation::Up)) => sleep_interval = (sleep_interval / 2).max(MIN_SLEEP_INTERVAL),
+                UserAction::Player(0, PlayerInput::Go(Orientation::Down)) => sleep_interval = (sleep_interval * 2).min(MAX_SLEEP_INTERVAL),
                 _ => ()
             }
         }
-        if game.iteration() > 1000 || game.world.turn_result != TurnResult::Ok {
+        if game.iteration() > config.max_steps || game.world.turn_result != TurnResult::Ok {
             break;
         }
     }
+    drop(view);
+    let finished = game.world.turn_result != TurnResult::Ok;
+    if let Some(leaderboard_file) = leaderboard_file.filter(|_| finished) {
+        let updated = Leaderboard::load(leaderboard_file).and_then(|mut leaderboard| {
+            leaderboard.record_game(&["q-table"], &game.world);
+            leaderboard.save(leaderboard_file)
+        });
+        if let Err(e) = updated {
+            eprintln!("Could not update leaderboard {}: {}", leaderboard_file, e);
+        }
+    }
+}
+
+fn main(){
+    let matches = App::new("learn snakes")
+        .about("Trains a tabular Q-learning or SARSA agent in single player games and prints its learning curve.")
+        .arg(Arg::with_name("episodes")
+            .short("e")
+            .long("episodes")
+            .value_name("EPISODES")
+            .help("Number of training episodes (default: 10000)."))
+        .arg(Arg::with_name("algorithm")
+            .long("algorithm")
+            .value_name("ALGORITHM")
+            .possible_values(&["q-learning", "sarsa"])
+            .help("Update rule (default: q-learning)."))
+        .arg(Arg::with_name("alpha")
+            .long("alpha")
+            .value_name("ALPHA")
+            .help("Learning rate (default: 0.1)."))
+        .arg(Arg::with_name("gamma")
+            .long("gamma")
+            .value_name("GAMMA")
+            .help("Discount of future rewards (default: 0.9)."))
+        .arg(Arg::with_name("epsilon")
+            .long("epsilon")
+            .value_name("EPSILON")
+            .help("Probability of exploring with a random action (default: 0.1)."))
+        .arg(Arg::with_name("epsilon-decay")
+            .long("epsilon-decay")
+            .value_name("FACTOR")
+            .help("Multiplies epsilon with this factor after every episode (default: 1, no decay)."))
+        .arg(Arg::with_name("qtable")
+            .short("q")
+            .long("qtable")
+            .value_name("FILE")
+            .help("Continues training the Q-table in this file and saves it there, without it training starts from scratch and nothing is saved."))
+        .arg(Arg::with_name("report")
+            .long("report")
+            .value_name("EPISODES")
+            .help("Prints the averages over this many episodes (default: 500)."))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .value_name("SEED")
+            .help("Seed of the first episode, the following episodes count up (default: 0)."))
+        .arg(Arg::with_name("size")
+            .short("s")
+            .long("size")
+            .value_name("GRID_SIZE")
+            .help("Number of cells along each grid axis (default: 10)."))
+        .arg(Arg::with_name("stones")
+             .short("o")
+             .long("stones")
+             .value_name("STONES")
+             .help("Number of stones in the world."))
+        .arg(Arg::with_name("walls")
+            .short("w")
+            .long("walls")
+            .help("Enable walls?"))
+        .arg(Arg::with_name("watch")
+            .long("watch")
+            .help("Watch the agent play a game after training."))
+        .arg(Arg::with_name("leaderboard")
+            .short("l")
+            .long("leaderboard")
+            .value_name("FILE")
+            .help("Rates the agent with the watched game in this leaderboard."))
+        .get_matches();
+    let size = match matches.value_of("size") {Some(v) => v.parse::<usize>().unwrap_or(10), _ => 10};
+    let config = EnvConfig {
+        rows: size,
+        cols: size,
+        stones: match matches.value_of("stones") {Some(v) => v.parse::<usize>().unwrap_or(3), _ => 3},
+        wall_collision: matches.occurrences_of("walls") == 1,
+        ..EnvConfig::default()
+    };
+    let episodes = match matches.value_of("episodes") {Some(v) => v.parse::<usize>().unwrap_or(10000), _ => 10000};
+    let report = match matches.value_of("report") {Some(v) => v.parse::<usize>().unwrap_or(500), _ => 500}.max(1);
+    let seed = match matches.value_of("seed") {Some(v) => v.parse::<u64>().unwrap_or(0), _ => 0};
+    let table_file = matches.value_of("qtable");
+    let table = match table_file {
+        Some(file) => match QTable::load(file) {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("Could not load q-table {}: {}", file, e);
+                process::exit(1);
+            }
+        },
+        None => QTable::new()
+    };
+    let algorithm = matches.value_of("algorithm").unwrap_or("q-learning").parse::<TdAlgorithm>().unwrap();
+    let mut learner = TabularLearner::new(table, algorithm, seed);
+    learner.alpha = match matches.value_of("alpha") {Some(v) => v.parse::<f32>().unwrap_or(learner.alpha), _ => learner.alpha};
+    learner.gamma = match matches.value_of("gamma") {Some(v) => v.parse::<f32>().unwrap_or(learner.gamma), _ => learner.gamma};
+    learner.epsilon = match matches.value_of("epsilon") {Some(v) => v.parse::<f32>().unwrap_or(learner.epsilon), _ => learner.epsilon};
+    let epsilon_decay = match matches.value_of("epsilon-decay") {Some(v) => v.parse::<f32>().unwrap_or(1.0), _ => 1.0};
+    let mut env = match Env::new(config.clone()) {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("Invalid environment: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut recent: Vec<EpisodeStats> = Vec::with_capacity(report);
+    println!("{:>8}  {:>10}  {:>10}  {:>9}", "Episodes", "Avg reward", "Avg length", "Avg steps");
+    for episode in 0..episodes {
+        recent.push(learner.train_episode(&mut env, seed + episode as u64));
+        learner.epsilon *= epsilon_decay;
+        if recent.len() == report || episode+1 == episodes {
+            let n = recent.len() as f32;
+            println!("{:>8}  {:>10.2}  {:>10.2}  {:>9.1}", episode+1,
+                recent.iter().map(|stats| stats.reward).sum::<f32>() / n,
+                recent.iter().map(|stats| stats.length as f32).sum::<f32>() / n,
+                recent.iter().map(|stats| stats.steps as f32).sum::<f32>() / n);
+            recent.clear();
+        }
+    }
+    if let Some(table_file) = table_file {
+        if let Err(e) = learner.table.save(table_file) {
+            eprintln!("Could not save q-table to {}: {}", table_file, e);
+        }
+    }
+    if matches.is_present("watch") {
+        watch(&config, learner.table, matches.value_of("leaderboard"));
+    }
 }
@@ -1,8 +1,12 @@
 extern crate gridsnakes;
 extern crate clap;
 
-use gridsnakes::view::{Controller, TermionView, NoopView};
-use gridsnakes::model::{Game, World, Orientation};
+use std::process;
+
+use gridsnakes::view::{Controller, TermionView};
+use gridsnakes::model::{Game, GameMode, World, Orientation, Remains, TurnResult, random_seed};
+use gridsnakes::ai::{Agent, AgentSpec, AGENT_KINDS};
+use gridsnakes::rating::Leaderboard;
 use clap::{App, Arg};
 
 
@@ -31,23 +35,131 @@ fn main() {
             .short("w")
             .long("walls")
             .help("Enable walls?"))
+        .arg(Arg::with_name("mode")
+            .long("mode")
+            .value_name("MODE")
+            .possible_values(&["classic", "tron"])
+            .help("Game mode, in tron mode snakes leave a permanent trail and the last snake alive wins."))
+        .arg(Arg::with_name("dead-snake-food")
+            .short("f")
+            .long("dead-snake-food")
+            .value_name("GROWTH")
+            .help("Multiplayer: dead snakes turn into food with this growth value per segment, the last snake alive wins."))
+        .arg(Arg::with_name("cutting")
+            .short("c")
+            .long("cutting")
+            .value_name("REMAINS")
+            .possible_values(&["food", "stones"])
+            .help("Multiplayer: biting another snake's body cuts it, the severed tail turns into food or stones."))
+        .arg(Arg::with_name("fog")
+            .long("fog")
+            .value_name("SIZE")
+            .help("Fog of war, every snake only sees a square of this many cells around its head."))
+        .arg(Arg::with_name("relative")
+            .long("relative")
+            .help("Left and right keys turn the snake relative to its heading, up and down do nothing."))
+        .arg(Arg::with_name("record")
+            .short("r")
+            .long("record")
+            .value_name("FILE")
+            .help("Records the game and saves the replay to this file."))
+        .arg(Arg::with_name("leaderboard")
+            .short("l")
+            .long("leaderboard")
+            .value_name("FILE")
+            .help("Rates the players with the finished game in this leaderboard."))
+        .arg(Arg::with_name("names")
+            .long("names")
+            .value_name("NAMES")
+            .help("Comma separated names of the players on the leaderboard (default: player1,player2)."))
+        .arg(Arg::with_name("bots")
+            .long("bots")
+            .value_name("N")
+            .help("Number of snakes controlled by an AI, in addition to the human players."))
+        .arg(Arg::with_name("bot-kind")
+            .long("bot-kind")
+            .value_name("AGENT")
+            .help(&format!("AI of the bots, e.g. space-explorer:depth=5 (default: space-explorer). Kinds: {}.", AGENT_KINDS.join(", "))))
         .get_matches();
-    let size = match matches.value_of("size") {Some(v) => match v.parse::<usize>() {Ok(n) => n, _ => 20}, _ => 20};
+    let size = match matches.value_of("size") {Some(v) => v.parse::<usize>().unwrap_or(20), _ => 20};
     let snakes = match matches.occurrences_of("multiplayer") {
         1 => 2,
         _ => 1
     };
-    let snacks = match matches.value_of("apples") {Some(v) => match v.parse::<usize>() {Ok(n) => n, _ => (size*size/100+1)}, _ => (size*size/100 + 1)};
-    let walls_enabled = match matches.occurrences_of("walls") {1 => true, _ => false};
-    let number_of_stones = match matches.value_of("stones") {Some(v) => match v.parse::<usize>() {Ok(n) => n, _ => 3}, _ => 3};
-    let view = TermionView::new().unwrap();
+    let snacks = match matches.value_of("apples") {Some(v) => match v.parse::<usize>() {Ok(n) => n, _ => size*size/100+1}, _ => size*size/100 + 1};
+    let walls_enabled = matches.occurrences_of("walls") == 1;
+    let number_of_stones = match matches.value_of("stones") {Some(v) => v.parse::<usize>().unwrap_or(3), _ => 3};
+    let bots = match matches.value_of("bots") {Some(v) => v.parse::<usize>().unwrap_or(0), _ => 0};
+    let bot_kind = match matches.value_of("bot-kind").unwrap_or("space-explorer").parse::<AgentSpec>() {
+        Ok(spec) => spec,
+        Err(e) => {
+            eprintln!("Could not create bots: {}", e);
+            process::exit(1);
+        }
+    };
+    let agents: Result<Vec<Box<dyn Agent>>, String> = (snakes..snakes+bots).map(|player| bot_kind.build(player, random_seed())).collect();
+    let agents = match agents {
+        Ok(agents) => agents,
+        Err(e) => {
+            eprintln!("Could not create bots: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut world = World::new(size, size);
+    for i in 0..snakes+bots {
+        if world.add_snake((1, (i*4+2)%size), Orientation::Down).is_err() {
+            eprintln!("There is no space for {} snakes on the grid.", snakes+bots);
+            process::exit(1);
+        }
+    }
+    let mut view = TermionView::new().unwrap();
+    view.relative_controls = matches.is_present("relative");
+    view.viewers = Some((0..snakes).collect());
     //let view = NoopView::new();
-    let mut controller = Controller::new(Game::new(World::new(size, size)), view);
-    for i in 0..snakes {
-        controller.game.world.add_snake((1, (i*4+2)%size), Orientation::Down).unwrap();
+    let mut controller = Controller::new(Game::new(world), view);
+    for (i, agent) in agents.into_iter().enumerate() {
+        controller.add_agent(snakes+i, agent);
     }
     controller.game.max_snacks = snacks;
     controller.game.world.wall_collision = walls_enabled;
+    if matches.value_of("mode") == Some("tron") {
+        controller.game.world.mode = GameMode::Tron;
+    }
+    controller.game.world.snake_cutting = match matches.value_of("cutting") {
+        Some("food") => Some(Remains::Food(1)),
+        Some("stones") => Some(Remains::Stones),
+        _ => None
+    };
+    controller.game.world.fog_of_war = matches.value_of("fog").and_then(|v| v.parse::<usize>().ok());
+    controller.game.world.dead_snake_food = matches.value_of("dead-snake-food").and_then(|v| v.parse::<usize>().ok());
     controller.game.world.place_stones_randomly(number_of_stones);
+    let replay_file = matches.value_of("record");
+    if replay_file.is_some() {
+        controller.record();
+    }
     controller.run_loop();
+    drop(controller.view);
+    if let (Some(replay), Some(replay_file)) = (controller.replay, replay_file) {
+        if let Err(e) = replay.save(replay_file) {
+            eprintln!("Could not save replay to {}: {}", replay_file, e);
+        }
+    }
+    // only finished games are rated
+    let finished = controller.game.world.turn_result != TurnResult::Ok;
+    if let Some(leaderboard_file) = matches.value_of("leaderboard").filter(|_| finished) {
+        let given: Vec<&str> = matches.value_of("names").unwrap_or("").split(',').map(|name| name.trim()).collect();
+        let names: Vec<String> = (0..snakes+bots).map(|i| match given.get(i) {
+            _ if i >= snakes => bot_kind.to_string(),
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => format!("player{}", i+1)
+        }).collect();
+        let world = &controller.game.world;
+        let updated = Leaderboard::load(leaderboard_file).and_then(|mut leaderboard| {
+            leaderboard.record_game(&names, world);
+            leaderboard.save(leaderboard_file)
+        });
+        if let Err(e) = updated {
+            eprintln!("Could not update leaderboard {}: {}", leaderboard_file, e);
+        }
+    }
 }
@@ -0,0 +1,79 @@
+extern crate gridsnakes;
+extern crate clap;
+
+use std::{process, thread, time};
+
+use gridsnakes::view::{TermionView, View, UserAction};
+use gridsnakes::model::TurnResult;
+use gridsnakes::replay::Replay;
+use clap::{App, Arg};
+
+/// Limits of the time between two steps, the speed keys stop there.
+const MIN_STEP_INTERVAL: time::Duration = time::Duration::from_millis(1);
+const MAX_STEP_INTERVAL: time::Duration = time::Duration::from_secs(10);
+
+fn main() {
+    let matches = App::new("replay snakes")
+        .about("Plays a recorded game again. Controls: space pauses, n steps while paused, + and - change the speed, q quits.")
+        .arg(Arg::with_name("FILE")
+            .index(1)
+            .help("Replay file to play (default: snakes.replay)."))
+        .arg(Arg::with_name("speed")
+            .short("s")
+            .long("speed")
+            .value_name("STEPS_PER_SECOND")
+            .help("Initial playback speed."))
+        .get_matches();
+    let file = matches.value_of("FILE").unwrap_or("snakes.replay");
+    let speed = match matches.value_of("speed") {Some(v) => v.parse::<u64>().unwrap_or(2), _ => 2};
+    let replay = match Replay::load(file) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("Could not load replay {}: {}", file, e);
+            process::exit(1);
+        }
+    };
+    let mut game = replay.game();
+    let mut turns = replay.turns().iter().enumerate();
+    let mut view = TermionView::new().unwrap();
+    let mut step_interval = time::Duration::from_millis(1000/speed.max(1)).max(MIN_STEP_INTERVAL);
+    let mut paused = false;
+    let mut desync = None;
+    view.read_user_inputs(); // drop any user input
+    view.draw_world(&game.world);
+    let mut next_step = time::Instant::now() + step_interval;
+    'playback: loop {
+        let mut step = false;
+        for a in view.read_user_inputs() {
+            match a {
+                UserAction::Quit => break 'playback,
+                UserAction::TogglePause => paused = !paused,
+                UserAction::Step => step = paused,
+                UserAction::SpeedUp => step_interval = (step_interval / 2).max(MIN_STEP_INTERVAL),
+                UserAction::SlowDown => step_interval = (step_interval * 2).min(MAX_STEP_INTERVAL),
+                _ => ()
+            }
+        }
+        if !paused && time::Instant::now() >= next_step {
+            step = true;
+        }
+        if step {
+            next_step = time::Instant::now() + step_interval;
+            if game.world.turn_result == TurnResult::Ok {
+                if let Some((turn, inputs)) = turns.next() {
+                    game.advance(inputs);
+                    view.draw_world(&game.world);
+                    if desync.is_none() && replay.hash_after(turn).is_some_and(|hash| hash != game.world.hash()) {
+                        desync = Some(turn);
+                    }
+                }
+            }
+        }
+        thread::sleep(time::Duration::from_millis(10));
+    }
+    drop(view);
+    if let Some(turn) = desync {
+        eprintln!("The replay diverged from the recorded game in turn {}.", turn+1);
+        process::exit(1);
+    }
+}
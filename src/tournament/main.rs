@@ -0,0 +1,323 @@
+extern crate gridsnakes;
+extern crate clap;
+
+use std::process;
+
+use gridsnakes::model::{Game, GameMode, World, Orientation, PlayerInput, Remains, TurnResult};
+use gridsnakes::ai::{Agent, AgentSpec, AGENT_KINDS};
+use gridsnakes::rating::Leaderboard;
+use clap::{App, Arg};
+
+/// Rules every game of the tournament is played with.
+struct Rules {
+    size: usize,
+    stones: usize,
+    walls: bool,
+    mode: GameMode,
+    dead_snake_food: Option<usize>,
+    cutting: Option<Remains>,
+    fog_of_war: Option<usize>,
+    max_turns: usize
+}
+
+/// Outcome of a game for every snake and the final world.
+struct Played {
+    results: Vec<GameResult>,
+    world: World
+}
+
+#[derive(Default)]
+struct Standing {
+    games: usize,
+    wins: usize,
+    draws: usize,
+    losses: usize,
+    total_length: usize,
+    total_survival: usize,
+    /// indices of the agents already played against
+    opponents: Vec<usize>
+}
+
+impl Standing {
+    fn points(&self) -> f64 {
+        self.wins as f64 + 0.5 * self.draws as f64
+    }
+}
+
+enum Outcome {
+    Win,
+    Draw,
+    Loss
+}
+
+struct GameResult {
+    outcome: Outcome,
+    length: usize,
+    survival: usize
+}
+
+/// Plays one game between the agents, `agents[i]` controls snake `i`. Fails if the snakes don't
+/// fit on the grid.
+fn play_game(rules: &Rules, agents: &mut [Box<dyn Agent>], seed: u64) -> Result<Played, String> {
+    let mut world = World::with_seed(rules.size, rules.size, seed);
+    world.wall_collision = rules.walls;
+    world.snake_cutting = rules.cutting;
+    world.dead_snake_food = rules.dead_snake_food;
+    world.fog_of_war = rules.fog_of_war;
+    for i in 0..agents.len() {
+        if world.add_snake((1, (i*4+2)%rules.size), Orientation::Down).is_err() {
+            return Err(format!("There is no space for {} snakes on the grid.", agents.len()));
+        }
+    }
+    world.place_stones_randomly(rules.stones);
+    let mut game = Game::new(world);
+    game.world.mode = rules.mode;
+    let mut survival = vec![0; agents.len()];
+    while game.iteration() < rules.max_turns {
+        let inputs: Vec<_> = agents.iter_mut().enumerate().map(|(s, agent)| match game.world.snakes[s].alive {
+            true => agent.decide(&game.observed_by(s)),
+            false => PlayerInput::DoNothing
+        }).collect();
+        game.advance(&inputs);
+        for (s, snake) in game.world.snakes.iter().enumerate() {
+            if snake.alive {
+                survival[s] += 1;
+            }
+        }
+        if game.world.turn_result != TurnResult::Ok {
+            break;
+        }
+    }
+    let world = &game.world;
+    let results = (0..agents.len()).map(|s| GameResult {
+        outcome: match world.turn_result {
+            TurnResult::GameOver if world.winners.contains(&s) => Outcome::Win,
+            TurnResult::GameOver | TurnResult::Draw if world.losers.contains(&s) => Outcome::Loss,
+            // games without a winner in time are draws
+            _ => Outcome::Draw
+        },
+        length: world.snakes[s].length,
+        survival: survival[s]
+    }).collect();
+    Ok(Played { results, world: game.world })
+}
+
+struct Tournament {
+    rules: Rules,
+    specs: Vec<AgentSpec>,
+    standings: Vec<Standing>,
+    games_per_pairing: usize,
+    next_seed: u64,
+    /// rates the agents with every game, if set
+    leaderboard: Option<Leaderboard>
+}
+
+impl Tournament {
+    /// Plays `games_per_pairing` games between agents `a` and `b`, switching sides every game.
+    fn play_pairing(&mut self, a: usize, b: usize) -> Result<(), String> {
+        for game in 0..self.games_per_pairing {
+            let seed = self.next_seed;
+            self.next_seed += 1;
+            let seats = if game % 2 == 0 { [a, b] } else { [b, a] };
+            let mut agents = self.build(&seats, seed)?;
+            let played = play_game(&self.rules, &mut agents, seed)?;
+            let names: Vec<String> = seats.iter().map(|seat| self.specs[*seat].to_string()).collect();
+            if let Some(leaderboard) = &mut self.leaderboard {
+                leaderboard.record_game(&names, &played.world);
+            }
+            for (seat, result) in seats.iter().zip(played.results) {
+                let standing = &mut self.standings[*seat];
+                standing.games += 1;
+                match result.outcome {
+                    Outcome::Win => standing.wins += 1,
+                    Outcome::Draw => standing.draws += 1,
+                    Outcome::Loss => standing.losses += 1
+                }
+                standing.total_length += result.length;
+                standing.total_survival += result.survival;
+            }
+        }
+        self.standings[a].opponents.push(b);
+        self.standings[b].opponents.push(a);
+        Ok(())
+    }
+    fn build(&self, seats: &[usize], seed: u64) -> Result<Vec<Box<dyn Agent>>, String> {
+        seats.iter().enumerate()
+            .map(|(player, agent)| self.specs[*agent].build(player, seed.wrapping_mul(31).wrapping_add(player as u64))
+                 .map_err(|e| format!("{}: {}", self.specs[*agent], e)))
+            .collect()
+    }
+    fn round_robin(&mut self) -> Result<(), String> {
+        for a in 0..self.specs.len() {
+            for b in a+1..self.specs.len() {
+                self.play_pairing(a, b)?;
+            }
+        }
+        Ok(())
+    }
+    /// Every round, agents with similar points play against each other, without repeating
+    /// pairings if possible. With an odd number of agents, the last one sits out.
+    fn swiss(&mut self, rounds: usize) -> Result<(), String> {
+        for _ in 0..rounds {
+            let mut unpaired = self.ranking();
+            while unpaired.len() > 1 {
+                let a = unpaired.remove(0);
+                let next = unpaired.iter().position(|b| !self.standings[a].opponents.contains(b)).unwrap_or(0);
+                let b = unpaired.remove(next);
+                self.play_pairing(a, b)?;
+            }
+        }
+        Ok(())
+    }
+    /// Agents sorted by points, best first.
+    fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.specs.len()).collect();
+        ranking.sort_by(|a, b| self.standings[*b].points().partial_cmp(&self.standings[*a].points()).unwrap());
+        ranking
+    }
+    fn print_results(&self) {
+        let width = self.specs.iter().map(|spec| spec.to_string().len()).max().unwrap_or(0).max(5);
+        println!("{:width$}  {:>5}  {:>4}  {:>4}  {:>4}  {:>6}  {:>10}  {:>12}", "Agent", "Games", "W", "D", "L", "Points", "Avg length", "Avg survival", width = width);
+        for i in self.ranking() {
+            let standing = &self.standings[i];
+            let games = standing.games.max(1) as f64;
+            println!("{:width$}  {:>5}  {:>4}  {:>4}  {:>4}  {:>6.1}  {:>10.1}  {:>12.1}",
+                self.specs[i].to_string(), standing.games, standing.wins, standing.draws, standing.losses, standing.points(),
+                standing.total_length as f64 / games, standing.total_survival as f64 / games, width = width);
+        }
+    }
+}
+
+fn main() {
+    let matches = App::new("snakes tournament")
+        .about("Plays agents against each other in headless two-player games and prints a results table.")
+        .arg(Arg::with_name("AGENT")
+            .index(1)
+            .multiple(true)
+            .required(true)
+            .help(&format!("Agents taking part, e.g. space-explorer:depth=5. Kinds: {}.", AGENT_KINDS.join(", "))))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .possible_values(&["round-robin", "swiss"])
+            .help("Who plays against whom (default: round-robin)."))
+        .arg(Arg::with_name("rounds")
+            .long("rounds")
+            .value_name("ROUNDS")
+            .help("Number of rounds of a swiss tournament (default: log2 of the number of agents)."))
+        .arg(Arg::with_name("games")
+            .short("g")
+            .long("games")
+            .value_name("GAMES")
+            .help("Games per pairing (default: 10)."))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .value_name("SEED")
+            .help("Seed of the first game, the following games count up (default: 0)."))
+        .arg(Arg::with_name("size")
+            .short("s")
+            .long("size")
+            .value_name("GRID_SIZE")
+            .help("Number of cells along each grid axis."))
+        .arg(Arg::with_name("stones")
+             .short("o")
+             .long("stones")
+             .value_name("STONES")
+             .help("Number of stones in the world."))
+        .arg(Arg::with_name("walls")
+            .short("w")
+            .long("walls")
+            .help("Enable walls?"))
+        .arg(Arg::with_name("mode")
+            .long("mode")
+            .value_name("MODE")
+            .possible_values(&["classic", "tron"])
+            .help("Game mode, in tron mode snakes leave a permanent trail and the last snake alive wins."))
+        .arg(Arg::with_name("dead-snake-food")
+            .short("f")
+            .long("dead-snake-food")
+            .value_name("GROWTH")
+            .help("Dead snakes turn into food with this growth value per segment, the last snake alive wins."))
+        .arg(Arg::with_name("cutting")
+            .short("c")
+            .long("cutting")
+            .value_name("REMAINS")
+            .possible_values(&["food", "stones"])
+            .help("Biting another snake's body cuts it, the severed tail turns into food or stones."))
+        .arg(Arg::with_name("fog")
+            .long("fog")
+            .value_name("SIZE")
+            .help("Fog of war, the agents only see a square of this many cells around their snake's head."))
+        .arg(Arg::with_name("leaderboard")
+            .short("l")
+            .long("leaderboard")
+            .value_name("FILE")
+            .help("Rates the agents with every game in this leaderboard."))
+        .arg(Arg::with_name("max-turns")
+            .long("max-turns")
+            .value_name("TURNS")
+            .help("Games still running after this many turns are draws (default: 1000)."))
+        .get_matches();
+    let specs: Result<Vec<AgentSpec>, String> = matches.values_of("AGENT").unwrap().map(|s| s.parse()).collect();
+    let specs = match specs {
+        Ok(specs) if specs.len() >= 2 => specs,
+        Ok(_) => {
+            eprintln!("A tournament needs at least two agents.");
+            process::exit(1);
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let rules = Rules {
+        size: match matches.value_of("size") {Some(v) => v.parse::<usize>().unwrap_or(20), _ => 20},
+        stones: match matches.value_of("stones") {Some(v) => v.parse::<usize>().unwrap_or(3), _ => 3},
+        walls: matches.occurrences_of("walls") == 1,
+        mode: if matches.value_of("mode") == Some("tron") { GameMode::Tron } else { GameMode::Classic },
+        dead_snake_food: matches.value_of("dead-snake-food").and_then(|v| v.parse::<usize>().ok()),
+        cutting: match matches.value_of("cutting") {
+            Some("food") => Some(Remains::Food(1)),
+            Some("stones") => Some(Remains::Stones),
+            _ => None
+        },
+        fog_of_war: matches.value_of("fog").and_then(|v| v.parse::<usize>().ok()),
+        max_turns: match matches.value_of("max-turns") {Some(v) => v.parse::<usize>().unwrap_or(1000), _ => 1000}
+    };
+    let default_rounds = (specs.len() as f64).log2().ceil() as usize;
+    let rounds = match matches.value_of("rounds") {Some(v) => v.parse::<usize>().unwrap_or(default_rounds), _ => default_rounds};
+    let mut tournament = Tournament {
+        rules,
+        standings: specs.iter().map(|_| Standing::default()).collect(),
+        specs,
+        games_per_pairing: match matches.value_of("games") {Some(v) => v.parse::<usize>().unwrap_or(10), _ => 10},
+        next_seed: match matches.value_of("seed") {Some(v) => v.parse::<u64>().unwrap_or(0), _ => 0},
+        leaderboard: None
+    };
+    let leaderboard_file = matches.value_of("leaderboard");
+    if let Some(file) = leaderboard_file {
+        match Leaderboard::load(file) {
+            Ok(leaderboard) => tournament.leaderboard = Some(leaderboard),
+            Err(e) => {
+                eprintln!("Could not load leaderboard {}: {}", file, e);
+                process::exit(1);
+            }
+        }
+    }
+    let played = match matches.value_of("format") {
+        Some("swiss") => tournament.swiss(rounds),
+        _ => tournament.round_robin()
+    };
+    if let Err(e) = played {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+    tournament.print_results();
+    if let (Some(file), Some(leaderboard)) = (leaderboard_file, &tournament.leaderboard) {
+        println!();
+        print!("{}", leaderboard);
+        if let Err(e) = leaderboard.save(file) {
+            eprintln!("Could not save leaderboard to {}: {}", file, e);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Randomized simulation of many seeded games with random inputs. The engine invariants are
+//! checked after every turn, failing games are shrunk to a minimal sequence of inputs.
+//!
+//! Set `SNAKES_STRESS_GAMES` to change the number of games per rule combination.
+
+extern crate gridsnakes;
+extern crate rand;
+
+use std::env;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use gridsnakes::model::{Game, GameMode, World, PlayerInput, Orientation, TurnResult, Remains};
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+const TURNS: usize = 300;
+
+type Inputs = Vec<Vec<PlayerInput>>;
+
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    rows: usize,
+    cols: usize,
+    snakes: usize,
+    stones: usize,
+    walls: bool,
+    lose_on_collision: bool,
+    max_snacks: usize,
+    dead_snake_food: Option<usize>,
+    snake_cutting: Option<Remains>,
+    mode: GameMode
+}
+
+#[derive(Debug)]
+struct Failure {
+    turn: usize,
+    message: String
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "turn {}: {}", self.turn, self.message)
+    }
+}
+
+fn games_per_rule() -> usize {
+    env::var("SNAKES_STRESS_GAMES").ok().and_then(|v| v.parse().ok()).unwrap_or(40)
+}
+
+fn new_game(rules: &Rules, seed: u64) -> Game {
+    let mut world = World::with_seed(rules.rows, rules.cols, seed);
+    world.wall_collision = rules.walls;
+    world.snake_cutting = rules.snake_cutting;
+    world.dead_snake_food = rules.dead_snake_food;
+    for i in 0..rules.snakes {
+        world.add_snake((1, i*3+1), Orientation::Down).unwrap();
+    }
+    world.place_stones_randomly(rules.stones);
+    let mut game = Game::new(world);
+    game.lose_on_collision = rules.lose_on_collision;
+    game.max_snacks = rules.max_snacks;
+    game.world.mode = rules.mode;
+    game
+}
+
+fn random_input<R: Rng>(rng: &mut R) -> PlayerInput {
+    match rng.gen_range(0u32, 8) {
+        0 => PlayerInput::Go(Orientation::Up),
+        1 => PlayerInput::Go(Orientation::Down),
+        2 => PlayerInput::Go(Orientation::Left),
+        3 => PlayerInput::Go(Orientation::Right),
+        4 => PlayerInput::TurnLeft,
+        5 => PlayerInput::TurnRight,
+        _ => PlayerInput::DoNothing
+    }
+}
+
+fn random_inputs(rules: &Rules, seed: u64) -> Inputs {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    (0..TURNS).map(|_| (0..rules.snakes).map(|_| random_input(&mut rng)).collect()).collect()
+}
+
+/// Plays `inputs` until the game ends, returns the world hash after every turn.
+fn play(rules: &Rules, seed: u64, inputs: &[Vec<PlayerInput>]) -> Result<Vec<u64>, Failure> {
+    let mut game = new_game(rules, seed);
+    if let Err(violations) = game.world.validate() {
+        return Err(Failure { turn: 0, message: format!("{:?}", violations) });
+    }
+    let mut hashes = Vec::new();
+    for (turn, turn_inputs) in inputs.iter().enumerate() {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| game.advance(turn_inputs)));
+        if let Err(e) = result {
+            let message = e.downcast_ref::<String>().cloned()
+                .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            return Err(Failure { turn: turn+1, message: format!("panic: {}", message) });
+        }
+        if let Err(violations) = game.world.validate() {
+            let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            return Err(Failure { turn: turn+1, message: messages.join(", ") });
+        }
+        hashes.push(game.world.hash());
+        if game.world.turn_result != TurnResult::Ok {
+            break;
+        }
+    }
+    Ok(hashes)
+}
+
+/// Removes turns and replaces inputs with `DoNothing` as long as the game still fails.
+fn shrink(rules: &Rules, seed: u64, mut inputs: Inputs) -> (Inputs, Failure) {
+    let mut failure = play(rules, seed, &inputs).expect_err("shrinking a game that doesn't fail");
+    inputs.truncate(failure.turn);
+    let mut chunk = inputs.len() / 2;
+    while chunk >= 1 {
+        let mut start = 0;
+        while start < inputs.len() {
+            let mut candidate = inputs.clone();
+            candidate.drain(start..usize::min(start+chunk, inputs.len()));
+            match play(rules, seed, &candidate) {
+                Err(f) => {
+                    candidate.truncate(f.turn);
+                    inputs = candidate;
+                    failure = f;
+                },
+                Ok(_) => start += chunk
+            }
+        }
+        chunk /= 2;
+    }
+    for turn in 0..inputs.len() {
+        for player in 0..rules.snakes {
+            if let PlayerInput::DoNothing = inputs[turn][player] {
+                continue;
+            }
+            let mut candidate = inputs.clone();
+            candidate[turn][player] = PlayerInput::DoNothing;
+            if let Err(f) = play(rules, seed, &candidate) {
+                inputs = candidate;
+                failure = f;
+            }
+        }
+    }
+    (inputs, failure)
+}
+
+fn stress(rules: Rules) {
+    let mut seeds = SmallRng::seed_from_u64(rules.rows as u64 * 1000 + rules.cols as u64);
+    for _ in 0..games_per_rule() {
+        let seed = u64::from(seeds.gen::<u32>());
+        let inputs = random_inputs(&rules, seed);
+        match play(&rules, seed, &inputs) {
+            Ok(hashes) => {
+                let again = play(&rules, seed, &inputs).ok();
+                assert_eq!(again, Some(hashes), "{:?}, seed {}: game is not deterministic", rules, seed);
+            },
+            Err(_) => {
+                let (inputs, failure) = shrink(&rules, seed, inputs);
+                panic!("{:?}, seed {}: {}\nminimal inputs: {:?}", rules, seed, failure, inputs);
+            }
+        }
+    }
+}
+
+fn all_rules(rows: usize, cols: usize, max_snakes: usize) -> Vec<Rules> {
+    let mut rules = Vec::new();
+    for snakes in 1..=max_snakes {
+        for &stones in &[0, 5] {
+            for &walls in &[false, true] {
+                let base = Rules {
+                    rows, cols, snakes, stones, walls,
+                    lose_on_collision: false,
+                    max_snacks: 3,
+                    dead_snake_food: None,
+                    snake_cutting: None,
+                    mode: GameMode::Classic
+                };
+                rules.push(base);
+                rules.push(Rules { lose_on_collision: true, ..base });
+                rules.push(Rules { lose_on_collision: true, mode: GameMode::Tron, ..base });
+                if snakes > 1 {
+                    rules.push(Rules { lose_on_collision: true, dead_snake_food: Some(1), ..base });
+                    rules.push(Rules { lose_on_collision: true, snake_cutting: Some(Remains::Food(1)), ..base });
+                    rules.push(Rules { snake_cutting: Some(Remains::Stones), ..base });
+                }
+            }
+        }
+    }
+    rules
+}
+
+#[test]
+fn square_grid() {
+    for rules in all_rules(10, 10, 3) {
+        stress(rules);
+    }
+}
+
+#[test]
+fn wide_grid() {
+    for rules in all_rules(5, 14, 3) {
+        stress(rules);
+    }
+}
+
+#[test]
+fn tall_grid() {
+    for rules in all_rules(13, 7, 2) {
+        stress(rules);
+    }
+}
+
+#[test]
+fn tiny_grid() {
+    for rules in all_rules(3, 4, 1) {
+        stress(rules);
+    }
+}